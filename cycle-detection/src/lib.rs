@@ -0,0 +1,135 @@
+// Cycle detection for simulations that eventually repeat a state: the state sequence is
+// `initial, step(initial), step(step(initial)), ...`.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+// States from index `tail_length` onwards repeat every `cycle_length` steps.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Cycle {
+    pub tail_length: usize,
+    pub cycle_length: usize,
+}
+
+impl Cycle {
+    // The first step whose state is the same as the state after `steps` steps.
+    pub fn equivalent_step(&self, steps: usize) -> usize {
+        if steps < self.tail_length {
+            steps
+        } else {
+            self.tail_length + (steps - self.tail_length) % self.cycle_length
+        }
+    }
+}
+
+// Brent's algorithm. Only two states are kept at a time, at the cost of simulating some steps twice.
+pub fn brent<S: PartialEq + Clone>(initial: &S, mut step: impl FnMut(&S) -> S) -> Cycle {
+    let mut power = 1;
+    let mut cycle_length = 1;
+    let mut tortoise = initial.clone();
+    let mut hare = step(initial);
+
+    while tortoise != hare {
+        if power == cycle_length {
+            tortoise = hare.clone();
+            power *= 2;
+            cycle_length = 0;
+        }
+        hare = step(&hare);
+        cycle_length += 1;
+    }
+
+    let mut tortoise = initial.clone();
+    let mut hare = initial.clone();
+    for _ in 0..cycle_length {
+        hare = step(&hare);
+    }
+
+    let mut tail_length = 0;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        tail_length += 1;
+    }
+
+    Cycle { tail_length, cycle_length }
+}
+
+enum Simulation<S> {
+    Finished(S),
+    Repeated(Vec<S>, Cycle),
+}
+
+// Simulates up to `steps` steps, stopping as soon as a state repeats.
+fn simulate<S: Hash + Eq + Clone>(initial: S, steps: usize, mut step: impl FnMut(&S) -> S) -> Simulation<S> {
+    let mut first_seen = HashMap::new();
+    let mut history = Vec::new();
+    let mut current = initial;
+
+    while history.len() < steps {
+        if let Some(&tail_length) = first_seen.get(&current) {
+            let cycle = Cycle { tail_length, cycle_length: history.len() - tail_length };
+            return Simulation::Repeated(history, cycle);
+        }
+
+        let next = step(&current);
+        first_seen.insert(current.clone(), history.len());
+        history.push(current);
+        current = next;
+    }
+
+    Simulation::Finished(current)
+}
+
+// Keeps a hashed history of every visited state, so each step is only simulated once.
+pub fn hashed<S: Hash + Eq + Clone>(initial: &S, step: impl FnMut(&S) -> S) -> Cycle {
+    match simulate(initial.clone(), usize::MAX, step) {
+        Simulation::Repeated(_, cycle) => cycle,
+        Simulation::Finished(_) => unreachable!("no simulation can run for usize::MAX steps without repeating"),
+    }
+}
+
+// The state after `steps` steps, which can be huge as long as the states start repeating early.
+pub fn state_after<S: Hash + Eq + Clone>(initial: S, steps: usize, step: impl FnMut(&S) -> S) -> S {
+    match simulate(initial, steps, step) {
+        Simulation::Finished(state) => state,
+        Simulation::Repeated(mut history, cycle) => history.swap_remove(cycle.equivalent_step(steps)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 0, 1, 2, 3, 4, 5, 6, 7, 3, 4, 5, 6, 7, 3, ...
+    fn step(state: &usize) -> usize {
+        if *state < 7 { state + 1 } else { 3 }
+    }
+
+    #[test]
+    fn test_find_cycle() {
+        let expected = Cycle { tail_length: 3, cycle_length: 5 };
+
+        assert_eq!(brent(&0, step), expected);
+        assert_eq!(hashed(&0, step), expected);
+        assert_eq!(brent(&5, step), Cycle { tail_length: 0, cycle_length: 5 });
+        assert_eq!(hashed(&5, step), Cycle { tail_length: 0, cycle_length: 5 });
+    }
+
+    #[test]
+    fn test_state_after() {
+        assert_eq!(state_after(0, 0, step), 0);
+        assert_eq!(state_after(0, 2, step), 2);
+        assert_eq!(state_after(0, 8, step), 3);
+        assert_eq!(state_after(0, 1_000_000_000_000, step), 3 + (1_000_000_000_000 - 3) % 5);
+    }
+
+    #[test]
+    fn test_equivalent_step() {
+        let cycle = Cycle { tail_length: 3, cycle_length: 5 };
+
+        assert_eq!(cycle.equivalent_step(2), 2);
+        assert_eq!(cycle.equivalent_step(8), 3);
+        assert_eq!(cycle.equivalent_step(14), 4);
+    }
+}
@@ -1,5 +1,9 @@
 use std::collections::HashSet;
 
+use polygon::Polygon;
+
+mod render;
+
 #[derive(Eq, PartialEq, Clone, Debug, Copy)]
 enum Direction {
     North,
@@ -18,17 +22,17 @@ impl Direction {
         }
     }
 
-    fn apply(&self, current: &(usize, usize)) -> (usize, usize) {
+    fn apply(&self, current: &(usize, usize)) -> Option<(usize, usize)> {
         match self {
-            Direction::North => (current.0, current.1 - 1),
-            Direction::South => (current.0, current.1 + 1),
-            Direction::East => (current.0 + 1, current.1),
-            Direction::West => (current.0 - 1, current.1),
+            Direction::North => Some((current.0, current.1.checked_sub(1)?)),
+            Direction::South => Some((current.0, current.1 + 1)),
+            Direction::East => Some((current.0 + 1, current.1)),
+            Direction::West => Some((current.0.checked_sub(1)?, current.1)),
         }
     }
 }
 
-#[derive(Eq, PartialEq, Clone, Debug)]
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
 enum Tile {
     Vertical,
     Horizontal,
@@ -71,6 +75,23 @@ impl Tile {
     }
 }
 
+const TILES: [Tile; 6] = [Tile::Vertical, Tile::Horizontal, Tile::NorthEast, Tile::NorthWest, Tile::SouthWest, Tile::SouthEast];
+
+#[derive(Eq, PartialEq, Debug)]
+enum StartError {
+    NotOnLoop,
+    Ambiguous(Vec<Tile>),
+}
+
+impl std::fmt::Display for StartError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StartError::NotOnLoop => write!(f, "start is not part of any loop"),
+            StartError::Ambiguous(tiles) => write!(f, "start could be any of {:?}", tiles),
+        }
+    }
+}
+
 struct Grid {
     start: (usize, usize),
     tiles: Vec<Vec<Option<Tile>>>,
@@ -92,55 +113,65 @@ impl Grid {
     }
 
     fn is_tile_up(&self, position: &(usize, usize)) -> bool {
-        position == &self.start || self.get_tile(position).map(|tile| tile.is_connection_up()).unwrap_or(false)
+        self.get_tile(position).map(|tile| tile.is_connection_up()).unwrap_or(false)
     }
-}
-
-const DIRECTIONS: [Direction; 4] = [Direction::North, Direction::South, Direction::East, Direction::West];
 
-fn get_loop(grid: &Grid) -> Vec<(usize, usize)> {
-    let mut result = Vec::new();
-
-    let start = grid.start;
+    // Walks the pipes leaving the start towards `direction` until it gets back to the start.
+    // Returns the visited positions (start included) and the direction of the last step,
+    // or None if the pipes lead to a dead end or out of the grid.
+    fn follow_pipes(&self, direction: Direction) -> Option<(Vec<(usize, usize)>, Direction)> {
+        let mut path = vec![self.start];
+        let mut last_direction = direction;
+        let mut current_pos = direction.apply(&self.start)?;
 
-    let mut path = DIRECTIONS.into_iter()
-        .map(|direction| (direction.clone(), direction.apply(&start)))  // assume start is not at the border (otherwise overflow would happen)
-        .filter(|(dir, pos)| grid.get_tile(pos).map(|tile| tile.has_connection(dir.opposite())).unwrap_or(false))
-        .last()
-        .expect("start has to have at least one connection");
+        while current_pos != self.start {
+            let next_direction = self.get_tile(&current_pos)?.get_next_direction(last_direction.opposite())?;
 
-    result.push(start);
+            path.push(current_pos);
 
-    while path.1 != start {
-        let last_direction = &mut path.0;
-        let current_pos = &mut path.1;
+            current_pos = next_direction.apply(&current_pos)?;
+            last_direction = next_direction;
+        }
 
-        let pipe = grid.get_tile(current_pos).expect("this always has to be a pipe");
+        Some((path, last_direction))
+    }
 
-        let next_direction = pipe
-            .get_next_direction(last_direction.opposite())
-            .expect("this always has to be a direction");
+    fn closes_loop(&self, tile: Tile) -> bool {
+        let (leaving, arriving) = tile.get_connections();
+        self.follow_pipes(leaving)
+            .map(|(_, last_direction)| last_direction.opposite() == arriving)
+            .unwrap_or(false)
+    }
 
-        result.push(*current_pos);
+    // Replaces the start with the only pipe that makes it part of a loop.
+    fn resolve_start(&mut self) -> Result<Tile, StartError> {
+        let candidates: Vec<Tile> = TILES.into_iter().filter(|&tile| self.closes_loop(tile)).collect();
 
-        *current_pos = next_direction.apply(current_pos);
-        *last_direction = next_direction;
+        match candidates.as_slice() {
+            [] => Err(StartError::NotOnLoop),
+            &[tile] => {
+                self.tiles[self.start.1][self.start.0] = Some(tile);
+                Ok(tile)
+            }
+            _ => Err(StartError::Ambiguous(candidates)),
+        }
     }
+}
+
+fn get_loop(grid: &Grid) -> Vec<(usize, usize)> {
+    let start_tile = grid.get_tile(&grid.start).expect("start is resolved while parsing");
+    let (direction, _) = start_tile.get_connections();
 
-    result
+    grid.follow_pipes(direction).expect("start is always part of a loop").0
 }
 
 fn get_longest_path(grid: &Grid) -> usize {
     get_loop(grid).len() / 2
 }
 
-fn get_enclosed_by_the_loop(grid: &Grid) -> usize {
+fn get_enclosed_tiles(grid: &Grid, grid_loop: &HashSet<(usize, usize)>) -> Vec<(usize, usize)> {
     let mut inside = Vec::new();
 
-    let grid_loop: HashSet<(usize, usize)> = HashSet::from_iter(get_loop(grid));
-
-    let mut result = 0;
-
     for y in 0..grid.get_y_size() {
         let mut is_inside = false;
         for x in 0..grid.get_x_size() {
@@ -148,19 +179,25 @@ fn get_enclosed_by_the_loop(grid: &Grid) -> usize {
             if grid_loop.contains(&position) && grid.is_tile_up(&position) {
                 is_inside = !is_inside;
             } else if is_inside && !grid_loop.contains(&position) {
-                result += 1;
                 inside.push(position);
             }
         }
     }
 
-    result
+    inside
+}
+
+// The loop through the centres of its tiles is a simple polygon, so Pick's theorem counts the tiles inside it.
+fn get_enclosed_by_the_loop(grid: &Grid) -> usize {
+    let vertices = get_loop(grid).into_iter().map(|(x, y)| (x as i64, y as i64)).collect();
+
+    Polygon::new(vertices).interior_points() as usize
 }
 
 mod parser {
-    use crate::{Grid, Tile};
+    use crate::{Grid, StartError, Tile};
 
-    pub(crate) fn parse_input(input: &str) -> Grid {
+    pub(crate) fn parse_input(input: &str) -> Result<Grid, StartError> {
         let mut start = None;
 
         let tiles = input.lines()
@@ -188,15 +225,99 @@ mod parser {
             })
             .collect::<Vec<_>>();
 
-        Grid { start: start.expect("start not found"), tiles }
+        let mut grid = Grid { start: start.expect("start not found"), tiles };
+        grid.resolve_start()?;
+
+        Ok(grid)
     }
 }
 
 fn main() {
     let input = include_str!("input.txt");
 
-    let grid = parser::parse_input(input);
+    let grid = parser::parse_input(input).unwrap_or_else(|error| panic!("invalid input: {error}"));
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["render"] | ["render", "ansi"] => print!("{}", render::render_ansi(&grid)),
+        ["render", "svg", path] => {
+            std::fs::write(path, render::render_svg(&grid)).expect("could not write svg file");
+            println!("Written to {path}");
+        }
+        [] => {
+            println!("Part 1: {:?}", get_longest_path(&grid));
+            println!("Part 2: {:?}", get_enclosed_by_the_loop(&grid));
+        }
+        _ => eprintln!("Usage: day-10 [render [ansi | svg <path>]]"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_input;
 
-    println!("Part 1: {:?}", get_longest_path(&grid));
-    println!("Part 2: {:?}", get_enclosed_by_the_loop(&grid));
+    #[test]
+    fn test_start_in_corner() {
+        let grid = parse_input("S-7\n|.|\nL-J").unwrap();
+
+        assert_eq!(grid.get_tile(&grid.start), Some(&Tile::SouthEast));
+        assert_eq!(get_longest_path(&grid), 4);
+        assert_eq!(get_enclosed_by_the_loop(&grid), 1);
+    }
+
+    #[test]
+    fn test_start_on_edges() {
+        let grid = parse_input("F-7\n|.|\nL-S").unwrap();
+        assert_eq!(grid.get_tile(&grid.start), Some(&Tile::NorthWest));
+
+        let grid = parse_input("F-7\nS.|\nL-J").unwrap();
+        assert_eq!(grid.get_tile(&grid.start), Some(&Tile::Vertical));
+        assert_eq!(get_enclosed_by_the_loop(&grid), 1);
+
+        let grid = parse_input("F-7\n|.|\nLSJ").unwrap();
+        assert_eq!(grid.get_tile(&grid.start), Some(&Tile::Horizontal));
+    }
+
+    #[test]
+    fn test_start_shape_affects_enclosed_tiles() {
+        let input = "...........
+.S-------7.
+.|F-----7|.
+.||.....||.
+.||.....||.
+.|L-7.F-J|.
+.|..|.|..|.
+.L--J.L--J.
+...........";
+        let grid = parse_input(input).unwrap();
+
+        assert_eq!(grid.get_tile(&grid.start), Some(&Tile::SouthEast));
+        assert_eq!(get_enclosed_by_the_loop(&grid), 4);
+    }
+
+    #[test]
+    fn test_pick_matches_scanline() {
+        let input = "FF7FSF7F7F7F7F7F---7
+L|LJ||||||||||||F--J
+FL-7LJLJ||||||LJL-77
+F--JF--7||LJLJ7F7FJ-
+L---JF-JLJ.||-FJLJJ7
+|F|F-JF---7F7-L7L|7|
+|FFJF7L7F-JF7|JL---7
+7-L-JL7||F7|L7F-7F7|
+L.L7LFJ|||||FJL7||LJ
+L7JLJL-JLJLJL--JLJ.L";
+        let grid = parse_input(input).unwrap();
+        let grid_loop = HashSet::from_iter(get_loop(&grid));
+
+        assert_eq!(get_enclosed_by_the_loop(&grid), 10);
+        assert_eq!(get_enclosed_tiles(&grid, &grid_loop).len(), 10);
+    }
+
+    #[test]
+    fn test_start_errors() {
+        assert_eq!(parse_input("S-.").err(), Some(StartError::NotOnLoop));
+        assert_eq!(parse_input("F-7..\n|.|..\nL-S-7\n..|.|\n..L-J").err(), Some(StartError::Ambiguous(vec![Tile::NorthWest, Tile::SouthEast])));
+    }
 }
@@ -0,0 +1,187 @@
+// Debug renderers for the pipe loop: ANSI text for the terminal or an SVG file.
+// Pipes that are not part of the loop are blanked out and enclosed tiles are shaded.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::{get_enclosed_tiles, get_loop, Direction, Grid};
+
+const RESET: &str = "\x1b[0m";
+const START_STYLE: &str = "\x1b[1;33m";
+const INSIDE_STYLE: &str = "\x1b[42m";
+
+const CELL_SIZE: usize = 10;
+const INSIDE_FILL: &str = "#4caf50";
+
+enum Cell {
+    Loop { connections: (Direction, Direction), start: bool },
+    Inside,
+    Outside,
+}
+
+fn offset(direction: Direction) -> (isize, isize) {
+    match direction {
+        Direction::North => (0, -1),
+        Direction::South => (0, 1),
+        Direction::East => (1, 0),
+        Direction::West => (-1, 0),
+    }
+}
+
+fn box_drawing_char(connections: (Direction, Direction)) -> char {
+    let has = |direction| connections.0 == direction || connections.1 == direction;
+
+    match (has(Direction::North), has(Direction::South), has(Direction::East), has(Direction::West)) {
+        (true, true, _, _) => '║',
+        (_, _, true, true) => '═',
+        (true, _, true, _) => '╚',
+        (true, _, _, true) => '╝',
+        (_, true, _, true) => '╗',
+        (_, true, true, _) => '╔',
+        _ => unreachable!("a pipe always connects two different directions"),
+    }
+}
+
+fn get_cells(grid: &Grid) -> Vec<Vec<Cell>> {
+    let grid_loop: HashSet<(usize, usize)> = HashSet::from_iter(get_loop(grid));
+    let inside: HashSet<(usize, usize)> = HashSet::from_iter(get_enclosed_tiles(grid, &grid_loop));
+
+    (0..grid.get_y_size())
+        .map(|y| {
+            (0..grid.get_x_size())
+                .map(|x| {
+                    let position = (x, y);
+                    if grid_loop.contains(&position) {
+                        let tile = grid.get_tile(&position).expect("loop is only made of pipes");
+                        Cell::Loop { connections: tile.get_connections(), start: position == grid.start }
+                    } else if inside.contains(&position) {
+                        Cell::Inside
+                    } else {
+                        Cell::Outside
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+pub(crate) fn render_ansi(grid: &Grid) -> String {
+    let mut output = String::new();
+
+    for row in get_cells(grid) {
+        for cell in row {
+            match cell {
+                Cell::Loop { connections, start: true } => {
+                    let _ = write!(output, "{START_STYLE}{}{RESET}", box_drawing_char(connections));
+                }
+                Cell::Loop { connections, start: false } => output.push(box_drawing_char(connections)),
+                Cell::Inside => {
+                    let _ = write!(output, "{INSIDE_STYLE} {RESET}");
+                }
+                Cell::Outside => output.push(' '),
+            }
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+pub(crate) fn render_svg(grid: &Grid) -> String {
+    let width = grid.get_x_size() * CELL_SIZE;
+    let height = grid.get_y_size() * CELL_SIZE;
+    let half = (CELL_SIZE / 2) as isize;
+
+    let mut svg = String::new();
+    let mut pipes = String::new();
+    let mut start = String::new();
+
+    let _ = writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#);
+    let _ = writeln!(svg, r#"<rect width="{width}" height="{height}" fill="white"/>"#);
+
+    for (y, row) in get_cells(grid).into_iter().enumerate() {
+        for (x, cell) in row.into_iter().enumerate() {
+            let (left, top) = (x * CELL_SIZE, y * CELL_SIZE);
+            let (center_x, center_y) = ((left + CELL_SIZE / 2) as isize, (top + CELL_SIZE / 2) as isize);
+
+            match cell {
+                Cell::Loop { connections, start: is_start } => {
+                    for direction in [connections.0, connections.1] {
+                        let (dx, dy) = offset(direction);
+                        let _ = write!(pipes, "M{center_x} {center_y}l{} {}", dx * half, dy * half);
+                    }
+                    if is_start {
+                        let _ = writeln!(start, r#"<circle cx="{center_x}" cy="{center_y}" r="{}" fill="red"/>"#, half / 2);
+                    }
+                }
+                Cell::Inside => {
+                    let _ = writeln!(svg, r#"<rect x="{left}" y="{top}" width="{CELL_SIZE}" height="{CELL_SIZE}" fill="{INSIDE_FILL}"/>"#);
+                }
+                Cell::Outside => {}
+            }
+        }
+    }
+
+    let _ = writeln!(svg, r#"<path d="{pipes}" stroke="black" stroke-width="2" stroke-linecap="square" fill="none"/>"#);
+    svg.push_str(&start);
+    svg.push_str("</svg>\n");
+
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_input;
+
+    const EXAMPLE: &str = "...........
+.S-------7.
+.|F-----7|.
+.||.....||.
+.||.....||.
+.|L-7.F-J|.
+.|..|.|..|.
+.L--J.L--J.
+...........";
+
+    fn strip_ansi(text: &str) -> String {
+        let mut result = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                chars.by_ref().find(|&c| c == 'm');
+            } else {
+                result.push(c);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_render_ansi() {
+        let grid = parse_input(EXAMPLE).unwrap();
+        let rendered = render_ansi(&grid);
+
+        assert_eq!(rendered.matches(INSIDE_STYLE).count(), 4);
+        assert_eq!(strip_ansi(&rendered).lines().collect::<Vec<_>>(), vec![
+            "           ",
+            " ╔═══════╗ ",
+            " ║╔═════╗║ ",
+            " ║║     ║║ ",
+            " ║║     ║║ ",
+            " ║╚═╗ ╔═╝║ ",
+            " ║  ║ ║  ║ ",
+            " ╚══╝ ╚══╝ ",
+            "           ",
+        ]);
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let grid = parse_input(EXAMPLE).unwrap();
+        let svg = render_svg(&grid);
+
+        assert_eq!(svg.matches(INSIDE_FILL).count(), 4);
+        assert_eq!(svg.matches("<circle").count(), 1);
+    }
+}
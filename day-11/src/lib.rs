@@ -0,0 +1,221 @@
+use std::collections::{BTreeMap, HashSet};
+
+use itertools::Itertools;
+
+#[derive(Debug)]
+pub struct GalaxyImage {
+    galaxies: Vec<(usize, usize)>,
+    expanded_rows: Vec<usize>,
+    expanded_columns: Vec<usize>,
+}
+
+impl GalaxyImage {
+    pub fn galaxies(&self) -> &[(usize, usize)] {
+        &self.galaxies
+    }
+}
+
+fn is_intersection(x1: usize, x2: usize, intersect: usize) -> bool {
+    let min = usize::min(x1, x2);
+    let max = usize::max(x1, x2);
+    min <= intersect && intersect <= max
+}
+
+fn get_vertical_intersections(x1: usize, x2: usize, galaxy_image: &GalaxyImage) -> usize {
+    galaxy_image.expanded_rows.iter().filter(|&&column| is_intersection(x1, x2, column)).count()
+}
+
+fn get_horizontal_intersections(y1: usize, y2: usize, galaxy_image: &GalaxyImage) -> usize {
+    galaxy_image.expanded_columns.iter().filter(|&&row| is_intersection(y1, y2, row)).count()
+}
+
+pub fn distance(galaxy1: (usize, usize), galaxy2: (usize, usize), galaxy_image: &GalaxyImage, growing_factor: usize) -> usize {
+    let (x1, y1) = galaxy1;
+    let (x2, y2) = galaxy2;
+
+    x2.abs_diff(x1) + y2.abs_diff(y1) + (get_horizontal_intersections(x1, x2, galaxy_image) + get_vertical_intersections(y1, y2, galaxy_image)) * (growing_factor - 1)
+}
+
+fn distances_from(galaxy: (usize, usize), galaxy_image: &GalaxyImage, growing_factor: usize) -> impl Iterator<Item=((usize, usize), usize)> + '_ {
+    galaxy_image.galaxies.iter()
+        .filter(move |&&other| other != galaxy)
+        .map(move |&other| (other, distance(galaxy, other, galaxy_image, growing_factor)))
+}
+
+// Returns the galaxy nearest to `galaxy` after expansion together with its distance.
+pub fn closest_galaxy(galaxy: (usize, usize), galaxy_image: &GalaxyImage, growing_factor: usize) -> Option<((usize, usize), usize)> {
+    distances_from(galaxy, galaxy_image, growing_factor).min_by_key(|&(_, distance)| distance)
+}
+
+// Returns the galaxy furthest from `galaxy` after expansion together with its distance.
+pub fn farthest_galaxy(galaxy: (usize, usize), galaxy_image: &GalaxyImage, growing_factor: usize) -> Option<((usize, usize), usize)> {
+    distances_from(galaxy, galaxy_image, growing_factor).max_by_key(|&(_, distance)| distance)
+}
+
+// Number of galaxy pairs for each distance after expansion.
+pub fn distance_histogram(galaxy_image: &GalaxyImage, growing_factor: usize) -> BTreeMap<usize, usize> {
+    galaxy_image.galaxies.iter()
+        .tuple_combinations()
+        .map(|(&galaxy1, &galaxy2)| distance(galaxy1, galaxy2, galaxy_image, growing_factor))
+        .counts()
+        .into_iter()
+        .collect()
+}
+
+// Maps each position to the sum of the widths of every row/column before it, returning them sorted.
+// Positions are sorted first so the offsets can be accumulated in a single sweep.
+fn weighted_coordinates(mut positions: Vec<usize>, width: impl Fn(usize) -> usize) -> Vec<usize> {
+    positions.sort_unstable();
+
+    let mut offset = 0;
+    let mut last_position = 0;
+
+    positions.into_iter().map(|position| {
+        offset += (last_position..position).map(&width).sum::<usize>();
+        last_position = position;
+        offset
+    }).collect()
+}
+
+fn sum_of_pairwise_differences(sorted_coordinates: &[usize]) -> usize {
+    let mut prefix_sum = 0;
+
+    sorted_coordinates.iter().enumerate().map(|(index, &coordinate)| {
+        let difference = coordinate * index - prefix_sum;
+        prefix_sum += coordinate;
+        difference
+    }).sum()
+}
+
+// Sum of the distances between every pair of galaxies when each column `x` is `column_width(x)` wide
+// and each row `y` is `row_width(y)` tall.
+pub fn distances_with_widths(galaxy_image: &GalaxyImage, column_width: impl Fn(usize) -> usize, row_width: impl Fn(usize) -> usize) -> usize {
+    let xs = galaxy_image.galaxies.iter().map(|&(x, _)| x).collect();
+    let ys = galaxy_image.galaxies.iter().map(|&(_, y)| y).collect();
+
+    sum_of_pairwise_differences(&weighted_coordinates(xs, column_width))
+        + sum_of_pairwise_differences(&weighted_coordinates(ys, row_width))
+}
+
+// The sum of all distances is linear in the growing factor: `base + (growing_factor - 1) * expansion`.
+#[derive(Debug, Eq, PartialEq)]
+pub struct DistanceSum {
+    pub base: usize,
+    pub expansion: usize,
+}
+
+impl DistanceSum {
+    pub fn with_growing_factor(&self, growing_factor: usize) -> usize {
+        self.base + (growing_factor - 1) * self.expansion
+    }
+}
+
+pub fn distance_sum(galaxy_image: &GalaxyImage) -> DistanceSum {
+    let is_expanded_column = |x| galaxy_image.expanded_columns.binary_search(&x).is_ok();
+    let is_expanded_row = |y| galaxy_image.expanded_rows.binary_search(&y).is_ok();
+
+    DistanceSum {
+        base: distances_with_widths(galaxy_image, |_| 1, |_| 1),
+        expansion: distances_with_widths(galaxy_image, |x| is_expanded_column(x) as usize, |y| is_expanded_row(y) as usize),
+    }
+}
+
+pub fn distances_for_growing_factors(galaxy_image: &GalaxyImage, growing_factors: &[usize]) -> Vec<usize> {
+    let distance_sum = distance_sum(galaxy_image);
+    growing_factors.iter().map(|&growing_factor| distance_sum.with_growing_factor(growing_factor)).collect()
+}
+
+pub fn distances(galaxy_image: &GalaxyImage, growing_factor: usize) -> usize {
+    distance_sum(galaxy_image).with_growing_factor(growing_factor)
+}
+
+pub fn parse(input: &str) -> GalaxyImage {
+    let mut galaxies = Vec::new();
+    let mut expanded_rows = Vec::new();
+    let mut not_expanded_columns = HashSet::new();
+
+    for (y, line) in input.lines().enumerate() {
+        let mut expanded_row = true;
+        for (x, character) in line.chars().enumerate() {
+            if character == '#' {
+                galaxies.push((x, y));
+                not_expanded_columns.insert(x);
+                expanded_row = false;
+            }
+        }
+        if expanded_row {
+            expanded_rows.push(y);
+        }
+    }
+
+    let expanded_columns = (0..input.lines().next().map(str::len).unwrap_or(0))
+        .filter(|&column| !not_expanded_columns.contains(&column))
+        .collect();
+
+    GalaxyImage {
+        galaxies,
+        expanded_rows,
+        expanded_columns,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part1() {
+        let galaxies = [(3, 0), (7, 1), (0, 2), (6, 4), (1, 5), (9, 6), (7, 8), (0, 9), (4, 9)];
+        let galaxy_image = GalaxyImage { galaxies: Vec::from_iter(galaxies), expanded_rows: vec![3, 7], expanded_columns: vec![2, 5, 8] };
+
+        assert_eq!(distance(galaxies[0], galaxies[0], &galaxy_image, 2), 0);
+        assert_eq!(distance(galaxies[0], galaxies[6], &galaxy_image, 2), 15);
+        assert_eq!(distance(galaxies[2], galaxies[5], &galaxy_image, 2), 17);
+        assert_eq!(distance(galaxies[7], galaxies[8], &galaxy_image, 2), 5);
+        assert_eq!(distances(&galaxy_image, 2), 374);
+        assert_eq!(distances(&galaxy_image, 10), 1030);
+        assert_eq!(distances(&galaxy_image, 100), 8410);
+        assert_eq!(distances_for_growing_factors(&galaxy_image, &[2, 10, 100]), vec![374, 1030, 8410]);
+    }
+
+    #[test]
+    fn test_fast_distances_match_pairwise() {
+        let galaxies = [(3, 0), (7, 1), (0, 2), (6, 4), (1, 5), (9, 6), (7, 8), (0, 9), (4, 9)];
+        let galaxy_image = GalaxyImage { galaxies: Vec::from_iter(galaxies), expanded_rows: vec![3, 7], expanded_columns: vec![2, 5, 8] };
+
+        for growing_factor in [1, 2, 7, 1000000] {
+            let pairwise: usize = galaxies.iter().combinations(2)
+                .map(|pair| distance(*pair[0], *pair[1], &galaxy_image, growing_factor))
+                .sum();
+
+            assert_eq!(distances(&galaxy_image, growing_factor), pairwise);
+        }
+    }
+
+    #[test]
+    fn test_galaxy_queries() {
+        let galaxy_image = parse("...#......\n.......#..\n#.........\n..........\n......#...\n.#........\n.........#\n..........\n.......#..\n#...#.....");
+
+        assert_eq!(galaxy_image.galaxies().len(), 9);
+        assert_eq!(closest_galaxy((0, 9), &galaxy_image, 2), Some(((4, 9), 5)));
+        assert_eq!(farthest_galaxy((0, 2), &galaxy_image, 2).map(|(_, distance)| distance), Some(17));
+        assert_eq!(closest_galaxy((0, 0), &parse("#.."), 2), None);
+
+        let histogram = distance_histogram(&galaxy_image, 2);
+        assert_eq!(histogram.values().sum::<usize>(), 36);
+        assert_eq!(histogram.iter().map(|(distance, count)| distance * count).sum::<usize>(), 374);
+        assert_eq!(histogram.last_key_value(), Some((&19, &1)));
+    }
+
+    #[test]
+    fn test_distances_with_widths() {
+        let galaxy_image = parse("#.#\n...\n.#.");
+
+        // columns: 0 -> 1 wide, 1 -> 10 wide, 2 -> 1 wide; rows: 0 -> 1 tall, 1 -> 100 tall, 2 -> 1 tall
+        let column_width = |x| if x == 1 { 10 } else { 1 };
+        let row_width = |y| if y == 1 { 100 } else { 1 };
+
+        // x distances: 11 + 1 + 10, y distances: 0 + 101 + 101
+        assert_eq!(distances_with_widths(&galaxy_image, column_width, row_width), 22 + 202);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,220 @@
+// Concrete arrangements of a condition record, derived from the same tables used to count them.
+
+use std::iter;
+
+use rand::Rng;
+
+use crate::{add_arrangements, Arrangements, SpringState, Springs};
+
+pub(crate) struct ArrangementTable<'a> {
+    springs: Springs<'a>,
+    groups_of_damaged: &'a [usize],
+    // columns[g][i] is the number of ways groups_of_damaged[g..] fit in states[i..], knowing states[i - 1] is not damaged
+    columns: Vec<Vec<Arrangements>>,
+}
+
+impl<'a> ArrangementTable<'a> {
+    pub(crate) fn new(states: &'a [SpringState], groups_of_damaged: &'a [usize]) -> ArrangementTable<'a> {
+        let springs = Springs::new(states);
+
+        let mut columns = vec![springs.last_column()];
+        for &group in groups_of_damaged.iter().rev() {
+            let column = springs.column_for_group(group, columns.last().expect("there is always a last column"));
+            columns.push(column);
+        }
+        columns.reverse();
+
+        ArrangementTable { springs, groups_of_damaged, columns }
+    }
+
+    pub(crate) fn count(&self) -> Arrangements {
+        self.columns[0][0]
+    }
+
+    // Arrangements are ordered by placing each group of damaged springs as early as possible.
+    pub(crate) fn arrangement_at(&self, mut index: Arrangements) -> Option<Vec<SpringState>> {
+        if index >= self.count() {
+            return None;
+        }
+
+        let mut arrangement = Vec::with_capacity(self.springs.len());
+        let (mut position, mut group_index) = (0, 0);
+
+        while position < self.springs.len() {
+            if let Some(&group) = self.groups_of_damaged.get(group_index) {
+                if self.springs.group_fits_at(position, group) {
+                    let next_position = self.springs.after_group(position, group);
+                    let with_group = self.columns[group_index + 1][next_position];
+
+                    if index < with_group {
+                        arrangement.extend(iter::repeat_n(SpringState::Damaged, group));
+                        arrangement.extend(iter::repeat_n(SpringState::Operational, next_position - position - group));
+                        position = next_position;
+                        group_index += 1;
+                        continue;
+                    }
+
+                    index -= with_group;
+                }
+            }
+
+            // the remaining arrangements all have an operational spring here
+            arrangement.push(SpringState::Operational);
+            position += 1;
+        }
+
+        Some(arrangement)
+    }
+
+    pub(crate) fn arrangements(&self) -> impl Iterator<Item=Vec<SpringState>> + '_ {
+        (0..self.count()).map(|index| self.arrangement_at(index).expect("index is always below the count"))
+    }
+
+    pub(crate) fn random_arrangement(&self, rng: &mut impl Rng) -> Option<Vec<SpringState>> {
+        match self.count() {
+            0 => None,
+            count => self.arrangement_at(rng.gen_range(0..count)),
+        }
+    }
+
+    // For every spring, the state it has in all arrangements, or None if arrangements disagree on it.
+    pub(crate) fn forced_states(&self) -> Vec<Option<SpringState>> {
+        let length = self.springs.len();
+        let total = self.count();
+
+        if total == 0 {
+            return vec![None; length];
+        }
+
+        // prefix[g][i] is the number of ways groups_of_damaged[..g] fit in states[..i], knowing states[i - 1] is not damaged
+        let mut prefix = vec![vec![0; length + 1]; self.groups_of_damaged.len() + 1];
+        prefix[0][0] = 1;
+
+        for position in 0..length {
+            for group_index in 0..prefix.len() {
+                let ways = prefix[group_index][position];
+                if ways == 0 {
+                    continue;
+                }
+
+                if self.springs.states[position] != SpringState::Damaged {
+                    prefix[group_index][position + 1] = add_arrangements(prefix[group_index][position + 1], ways);
+                }
+
+                if let Some(&group) = self.groups_of_damaged.get(group_index) {
+                    if self.springs.group_fits_at(position, group) {
+                        let next_position = self.springs.after_group(position, group);
+                        prefix[group_index + 1][next_position] = add_arrangements(prefix[group_index + 1][next_position], ways);
+                    }
+                }
+            }
+        }
+
+        // Number of arrangements where each spring is damaged, accumulated as a difference array over every group placement.
+        // Intermediate values may wrap around but the final sums are always between 0 and the total.
+        let mut damaged_difference: Vec<Arrangements> = vec![0; length + 1];
+
+        for (group_index, &group) in self.groups_of_damaged.iter().enumerate() {
+            for position in 0..length {
+                if !self.springs.group_fits_at(position, group) {
+                    continue;
+                }
+
+                let next_position = self.springs.after_group(position, group);
+                let placements = prefix[group_index][position] * self.columns[group_index + 1][next_position];
+
+                damaged_difference[position] = damaged_difference[position].wrapping_add(placements);
+                damaged_difference[position + group] = damaged_difference[position + group].wrapping_sub(placements);
+            }
+        }
+
+        let mut damaged: Arrangements = 0;
+        damaged_difference[..length].iter()
+            .map(|&difference| {
+                damaged = damaged.wrapping_add(difference);
+                match damaged {
+                    0 => Some(SpringState::Operational),
+                    damaged if damaged == total => Some(SpringState::Damaged),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::{format_states, parse_line, parse_spring_statuses};
+
+    fn groups_of(arrangement: &[SpringState]) -> Vec<usize> {
+        arrangement
+            .split(|&state| state == SpringState::Operational)
+            .filter(|group| !group.is_empty())
+            .map(<[SpringState]>::len)
+            .collect()
+    }
+
+    fn is_valid(arrangement: &[SpringState], states: &[SpringState], groups_of_damaged: &[usize]) -> bool {
+        arrangement.len() == states.len()
+            && arrangement.iter().zip(states).all(|(&arranged, &state)| state == SpringState::Unknown || arranged == state)
+            && groups_of(arrangement) == groups_of_damaged
+    }
+
+    #[test]
+    fn test_arrangements() {
+        let (states, groups_of_damaged) = parse_line("?###???????? 3,2,1");
+        let table = ArrangementTable::new(&states, &groups_of_damaged);
+
+        let arrangements: Vec<_> = table.arrangements().collect();
+
+        assert_eq!(arrangements.len(), 10);
+        assert_eq!(arrangements.iter().collect::<HashSet<_>>().len(), 10);
+        assert!(arrangements.iter().all(|arrangement| is_valid(arrangement, &states, &groups_of_damaged)));
+        assert_eq!(format_states(&arrangements[0]), ".###.##.#...");
+        assert_eq!(table.arrangement_at(10), None);
+    }
+
+    #[test]
+    fn test_no_arrangements() {
+        let states = parse_spring_statuses("#.#");
+        let table = ArrangementTable::new(&states, &[3]);
+
+        assert_eq!(table.count(), 0);
+        assert_eq!(table.arrangements().count(), 0);
+        assert_eq!(table.random_arrangement(&mut StdRng::seed_from_u64(0)), None);
+        assert_eq!(table.forced_states(), vec![None; 3]);
+    }
+
+    #[test]
+    fn test_random_arrangement() {
+        let (states, groups_of_damaged) = parse_line(".??..??...?##. 1,1,3");
+        let table = ArrangementTable::new(&states, &groups_of_damaged);
+        let mut rng = StdRng::seed_from_u64(12);
+
+        let seen: HashSet<_> = (0..200).map(|_| table.random_arrangement(&mut rng).unwrap()).collect();
+
+        assert!(seen.iter().all(|arrangement| is_valid(arrangement, &states, &groups_of_damaged)));
+        assert_eq!(seen.len(), 4);
+    }
+
+    #[test]
+    fn test_forced_states() {
+        let format_forced = |line: &str| {
+            let (states, groups_of_damaged) = parse_line(line);
+            ArrangementTable::new(&states, &groups_of_damaged).forced_states().iter()
+                .map(|state| state.map(SpringState::to_char).unwrap_or('?'))
+                .collect::<String>()
+        };
+
+        assert_eq!(format_forced("???.### 1,1,3"), "#.#.###");
+        assert_eq!(format_forced("?###???????? 3,2,1"), ".###.???????");
+        assert_eq!(format_forced(".??..??...?##. 1,1,3"), ".??..??...###.");
+        assert_eq!(format_forced("????.######..#####. 1,6,5"), "????.######..#####.");
+    }
+}
@@ -1,4 +1,6 @@
-use std::collections::HashMap;
+use crate::arrangements::ArrangementTable;
+
+mod arrangements;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum SpringState {
@@ -7,96 +9,157 @@ enum SpringState {
     Unknown,
 }
 
-fn count_possible_arrangements(states: &[SpringState], groups_of_damaged: &[usize]) -> usize {
-    fn count_possible_arrangements_inner(
-        cache: &mut HashMap<(Vec<SpringState>, Vec<usize>, usize), usize>,
-        states: &[SpringState],
-        groups: &[usize],
-        accumulated_damage: usize,
-    ) -> usize {
-        if states.is_empty() {
-            if groups.is_empty() {
-                return 1;
-            }
-
-            if groups.len() == 1 && groups[0] == accumulated_damage {
-                return 1;
-            }
-
-            return 0;
+impl SpringState {
+    fn to_char(self) -> char {
+        match self {
+            SpringState::Operational => '.',
+            SpringState::Damaged => '#',
+            SpringState::Unknown => '?',
         }
+    }
+}
 
-        return match states[0] {
-            SpringState::Operational => {
-                if accumulated_damage == 0 {
-                    return count_possible_arrangements_inner(cache, &states[1..], groups, accumulated_damage);
-                }
+// Arrangement counts overflow `usize` quickly once lines are unfolded many times.
+type Arrangements = u128;
 
-                if groups.is_empty() || accumulated_damage != groups[0] {
-                    return 0;
-                }
+fn add_arrangements(a: Arrangements, b: Arrangements) -> Arrangements {
+    a.checked_add(b).expect("number of arrangements does not fit in an u128")
+}
 
-                count_possible_arrangements_inner(cache, &states[1..], &groups[1..], 0)
-            }
+// Precomputed lookups over a line of springs shared by the counting and enumeration code.
+struct Springs<'a> {
+    states: &'a [SpringState],
+    // operational_before[i] is the number of operational springs in states[..i]
+    operational_before: Vec<usize>,
+}
 
-            SpringState::Damaged => {
-                if groups.is_empty() || accumulated_damage + 1 > groups[0] {
-                    return 0;
-                }
+impl<'a> Springs<'a> {
+    fn new(states: &'a [SpringState]) -> Springs<'a> {
+        let mut operational_before = vec![0; states.len() + 1];
+        for (i, state) in states.iter().enumerate() {
+            operational_before[i + 1] = operational_before[i] + (*state == SpringState::Operational) as usize;
+        }
 
-                count_possible_arrangements_inner(cache, &states[1..], groups, accumulated_damage + 1)
-            }
+        Springs { states, operational_before }
+    }
+
+    fn len(&self) -> usize {
+        self.states.len()
+    }
 
-            SpringState::Unknown => {
-                if let Some(answer) = cache.get(&(states.to_vec(), groups.to_vec(), accumulated_damage)).copied() {
-                    return answer;
-                }
+    // a group fits in states[start..start + group] when none of them is operational and it is not followed by a damaged spring
+    fn group_fits_at(&self, start: usize, group: usize) -> bool {
+        let end = start + group;
+        end <= self.len()
+            && self.operational_before[end] == self.operational_before[start]
+            && self.states.get(end) != Some(&SpringState::Damaged)
+    }
 
-                let mut arrangements = 0;
+    // Index right after a group placed at `start`, skipping the operational spring that has to follow it.
+    fn after_group(&self, start: usize, group: usize) -> usize {
+        usize::min(start + group + 1, self.len())
+    }
 
-                if accumulated_damage == 0 {
-                    arrangements += count_possible_arrangements_inner(cache, &states[1..], groups, accumulated_damage);
-                }
+    // column[i] is the number of ways no more groups fit in states[i..].
+    fn last_column(&self) -> Vec<Arrangements> {
+        let mut column = vec![1; self.len() + 1];
+        for i in (0..self.len()).rev() {
+            column[i] = if self.states[i] == SpringState::Damaged { 0 } else { column[i + 1] };
+        }
+        column
+    }
 
-                if !groups.is_empty() && accumulated_damage < groups[0] {
-                    arrangements += count_possible_arrangements_inner(cache, &states[1..], groups, accumulated_damage + 1);
-                }
+    // column[i] is the number of ways `group` followed by the groups counted in `next_column` fit in states[i..],
+    // knowing states[i - 1] is not damaged.
+    fn column_for_group(&self, group: usize, next_column: &[Arrangements]) -> Vec<Arrangements> {
+        let mut column = vec![0; self.len() + 1];
 
-                if !groups.is_empty() && accumulated_damage == groups[0] {
-                    arrangements += count_possible_arrangements_inner(cache, &states[1..], &groups[1..], 0);
-                }
+        for i in (0..self.len()).rev() {
+            if self.states[i] != SpringState::Damaged {
+                column[i] = add_arrangements(column[i], column[i + 1]);
+            }
 
-                cache.insert((states.to_vec(), groups.to_vec(), accumulated_damage), arrangements);
-                arrangements
+            if self.group_fits_at(i, group) {
+                column[i] = add_arrangements(column[i], next_column[self.after_group(i, group)]);
             }
-        };
+        }
+
+        column
     }
-    count_possible_arrangements_inner(&mut HashMap::new(), states, groups_of_damaged, 0)
+}
+
+fn count_possible_arrangements(states: &[SpringState], groups_of_damaged: &[usize]) -> Arrangements {
+    let springs = Springs::new(states);
+
+    // Groups are tabulated from the last one to the first, keeping only the column of the group after the current one.
+    groups_of_damaged.iter().rev()
+        .fold(springs.last_column(), |next_column, &group| springs.column_for_group(group, &next_column))[0]
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let [command, line @ ..] = args.as_slice() {
+        match (command.as_str(), line) {
+            ("explain", [line]) => explain(line, 20),
+            ("explain", [line, limit]) => explain(line, limit.parse().expect("limit must be a number")),
+            _ => eprintln!("Usage: day-12 [explain \"<condition record>\" [limit]]"),
+        }
+        return;
+    }
+
     let input = include_str!("input.txt");
     let input = parse_input(input);
 
     let instant = std::time::Instant::now();
-    let part1 = input.iter().map(|(states, groups_of_damaged)| count_possible_arrangements(states, groups_of_damaged)).sum::<usize>();
+    let part1 = input.iter().map(|(states, groups_of_damaged)| count_possible_arrangements(states, groups_of_damaged)).sum::<Arrangements>();
     println!("Part 1: {} in {:?}", part1, instant.elapsed());
 
-    let part2 = input.iter().map(|(states, groups_of_damaged)| count_possible_arrangements_5_times(states, &groups_of_damaged)).sum::<usize>();
+    let part2 = input.iter().map(|(states, groups_of_damaged)| count_possible_arrangements_5_times(states, groups_of_damaged)).sum::<Arrangements>();
     println!("Part 2: {} in {:?}", part2, instant.elapsed());
 }
 
-fn count_possible_arrangements_5_times(states: &[SpringState], groups_of_damaged: &[usize]) -> usize {
+fn explain(line: &str, limit: usize) {
+    let (states, groups_of_damaged) = parse_line(line);
+    let table = ArrangementTable::new(&states, &groups_of_damaged);
+
+    let forced = table.forced_states().iter()
+        .map(|state| state.map(SpringState::to_char).unwrap_or('?'))
+        .collect::<String>();
+
+    println!("{} arrangements", table.count());
+    println!("Forced: {}", forced);
+    if let Some(random) = table.random_arrangement(&mut rand::thread_rng()) {
+        println!("Random: {}", format_states(&random));
+    }
+    for arrangement in table.arrangements().take(limit) {
+        println!("        {}", format_states(&arrangement));
+    }
+}
+
+fn unfold(states: &[SpringState], groups_of_damaged: &[usize], times: usize) -> (Vec<SpringState>, Vec<usize>) {
     let mut states = states.to_vec();
     states.push(SpringState::Unknown);
-    states = states.repeat(5);
+    states = states.repeat(times);
     states.pop();
 
-    count_possible_arrangements(&states, &groups_of_damaged.repeat(5))
+    (states, groups_of_damaged.repeat(times))
+}
+
+fn count_possible_arrangements_unfolded(states: &[SpringState], groups_of_damaged: &[usize], times: usize) -> Arrangements {
+    let (states, groups_of_damaged) = unfold(states, groups_of_damaged, times);
+    count_possible_arrangements(&states, &groups_of_damaged)
+}
+
+fn count_possible_arrangements_5_times(states: &[SpringState], groups_of_damaged: &[usize]) -> Arrangements {
+    count_possible_arrangements_unfolded(states, groups_of_damaged, 5)
+}
+
+fn format_states(states: &[SpringState]) -> String {
+    states.iter().map(|state| state.to_char()).collect()
 }
 
 fn parse_input(input: &str) -> Vec<(Vec<SpringState>, Vec<usize>)> {
-    input.lines().map(|line| parse_line(line)).collect()
+    input.lines().map(parse_line).collect()
 }
 
 fn parse_line(input: &str) -> (Vec<SpringState>, Vec<usize>) {
@@ -134,4 +197,35 @@ mod tests {
         assert_eq!(count_possible_arrangements(&parse_spring_statuses("?###????????"), &[3, 2, 1]), 10);
         assert_eq!(count_possible_arrangements_5_times(&parse_spring_statuses("???.###"), &[1, 1, 3]), 1);
     }
+
+    #[test]
+    fn test_unfolded_arrangements() {
+        let cases = [
+            (".??..??...?##. 1,1,3", 16384),
+            ("?#?#?#?#?#?#?#? 1,3,1,6", 1),
+            ("????.#...#... 4,1,1", 16),
+            ("????.######..#####. 1,6,5", 2500),
+            ("?###???????? 3,2,1", 506250),
+        ];
+
+        for (line, expected) in cases {
+            let (states, groups_of_damaged) = parse_line(line);
+            assert_eq!(count_possible_arrangements_5_times(&states, &groups_of_damaged), expected);
+        }
+
+        let (states, groups_of_damaged) = parse_line("?###???????? 3,2,1");
+        assert_eq!(count_possible_arrangements_unfolded(&states, &groups_of_damaged, 1), 10);
+        assert_eq!(count_possible_arrangements_unfolded(&states, &groups_of_damaged, 2), 150);
+    }
+
+    #[test]
+    fn test_long_lines() {
+        // 12 single damaged springs in 2000 unknowns: choose(2000 - 12 + 1, 12), which does not fit in a u64
+        let states = vec![SpringState::Unknown; 2000];
+        assert_eq!(count_possible_arrangements(&states, &[1; 12]), 7741793818861435694528739977496);
+
+        // every extra fold of this line multiplies the arrangements by 15
+        let (states, groups_of_damaged) = parse_line("?###???????? 3,2,1");
+        assert_eq!(count_possible_arrangements_unfolded(&states, &groups_of_damaged, 30), 10 * 15u128.pow(29));
+    }
 }
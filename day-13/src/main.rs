@@ -4,177 +4,191 @@ enum Tile {
     Rock,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 struct Map {
-    tiles: Vec<Vec<Tile>>,
+    // rows[y] has bit x set when the tile at (x, y) is a rock, and columns[x] has bit y set for the same tile.
+    // Bit i of a line is bit i % 64 of its word i / 64.
+    rows: Vec<Vec<u64>>,
+    columns: Vec<Vec<u64>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 enum MirrorDirection {
     Horizontal,
     Vertical,
 }
 
-impl Map {
-    fn width(&self) -> usize {
-        self.tiles.get(0).map(Vec::len).unwrap_or(0)
-    }
-
-    fn height(&self) -> usize {
-        self.tiles.len()
-    }
+// A reflection line placed after `position` rows (horizontal) or columns (vertical).
+// The smudges are the (x, y) tiles that have to be flipped so both sides mirror each other exactly.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Reflection {
+    direction: MirrorDirection,
+    position: usize,
+    smudges: Vec<(usize, usize)>,
+}
 
-    fn match_columns(&self, column1: usize, column2: usize) -> bool {
-        for y in 0..self.height() {
-            if self.tiles[y][column1] != self.tiles[y][column2] {
-                return false;
-            }
+impl Reflection {
+    fn summarize(&self) -> usize {
+        match self.direction {
+            MirrorDirection::Horizontal => 100 * self.position,
+            MirrorDirection::Vertical => self.position,
         }
-        true
     }
+}
 
-    fn match_rows(&self, row1: usize, row2: usize) -> bool {
-        self.tiles[row1] == self.tiles[row2]
-    }
+// The tile that has to be flipped for part 2, and the map before and after flipping it.
+#[derive(Debug, Eq, PartialEq)]
+struct SmudgeFix {
+    smudge: (usize, usize),
+    old_reflection: Reflection,
+    new_reflection: Reflection,
+    corrected: Map,
+}
 
-    fn generic_get_mirror_position(&self, lines_match: fn(&Map, usize, usize) -> bool, size_to_loop: usize) -> Option<usize> {
-        'outer: for current in 1..size_to_loop {
-            let previous_line = current - 1;
+// Finds every position where `lines` mirror each other with exactly `smudges` different bits,
+// returning the position with the differing bits, as (line, bit), of the lines before it.
+fn find_reflections(lines: &[Vec<u64>], smudges: u32) -> Vec<(usize, Vec<(usize, usize)>)> {
+    let mut reflections = vec![];
 
-            if lines_match(self, previous_line, current) {
-                let reflection_pos = previous_line;
+    'outer: for position in 1..lines.len() {
+        let mut differences = 0;
+        let mut different_bits = vec![];
 
-                for current_rest in current + 1..size_to_loop {
-                    if reflection_pos * 2 + 1 < current_rest {
-                        continue;
-                    }
+        for (before, after) in (0..position).rev().zip(position..lines.len()) {
+            for (index, (word_before, word_after)) in lines[before].iter().zip(&lines[after]).enumerate() {
+                let difference = word_before ^ word_after;
+                differences += difference.count_ones();
 
-                    if !lines_match(self, current_rest, reflection_pos * 2 + 1 - current_rest) {
-                        continue 'outer;
-                    }
+                if differences > smudges {
+                    continue 'outer;
                 }
 
-                return Some(reflection_pos + 1);
+                different_bits.extend(bit_positions(difference).map(|bit| (before, index * 64 + bit)));
             }
         }
-        None
-    }
-
-    fn get_vertical_mirror_position(&self) -> Option<usize> {
-        self.generic_get_mirror_position(Map::match_columns, self.width())
-    }
-
-    fn get_horizontal_mirror_position(&self) -> Option<usize> {
-        self.generic_get_mirror_position(Map::match_rows, self.height())
-    }
 
-    fn get_columns_difference(&self, column1: usize, column2: usize) -> usize {
-        let mut difference = 0;
-        for y in 0..self.height() {
-            if self.tiles[y][column1] != self.tiles[y][column2] {
-                difference += 1;
-            }
+        if differences == smudges {
+            reflections.push((position, different_bits));
         }
-        difference
-    }
-
-    fn get_rows_difference(&self, row1: usize, row2: usize) -> usize {
-        let row1 = &self.tiles[row1];
-        let row2 = &self.tiles[row2];
-
-        row1.iter()
-            .zip(row2.iter())
-            .filter(|(tile1, tile2)| tile1 != tile2)
-            .count()
     }
 
-    fn generic_get_mirror_position_with_differences(&self, difference_match: fn(&Map, usize, usize) -> usize, size_to_loop: usize) -> Option<usize> {
-        'outer: for current in 1..size_to_loop {
-            let previous_line = current - 1;
-
-            let difference = difference_match(self, previous_line, current);
-            let mut has_changed = difference > 0;
-            if difference <= 1 {
-                let reflection_pos = previous_line;
-
-                for current_rest in current + 1..size_to_loop {
-                    if reflection_pos * 2 + 1 < current_rest {
-                        continue;
-                    }
+    reflections
+}
 
-                    let i = difference_match(self, current_rest, reflection_pos * 2 + 1 - current_rest);
+fn bit_positions(mut mask: u64) -> impl Iterator<Item=usize> {
+    std::iter::from_fn(move || {
+        if mask == 0 {
+            return None;
+        }
+        let bit = mask.trailing_zeros() as usize;
+        mask &= mask - 1;
+        Some(bit)
+    })
+}
 
-                    if i > 1 || i == 1 && has_changed {
-                        continue 'outer;
-                    }
+impl Map {
+    fn new(tiles: Vec<Vec<Tile>>) -> Map {
+        let width = tiles.first().map(Vec::len).unwrap_or(0);
 
-                    has_changed = has_changed || i > 0;
-                }
+        let mut rows = vec![vec![0; width.div_ceil(64)]; tiles.len()];
+        let mut columns = vec![vec![0; tiles.len().div_ceil(64)]; width];
 
-                if has_changed {
-                    return Some(reflection_pos + 1);
+        for (y, row) in tiles.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                if *tile == Tile::Rock {
+                    rows[y][x / 64] |= 1 << (x % 64);
+                    columns[x][y / 64] |= 1 << (y % 64);
                 }
             }
         }
-        None
+
+        Map { rows, columns }
     }
 
-    fn get_mirror_position(&self) -> (MirrorDirection, usize) {
-        if let Some(position) = self.get_vertical_mirror_position() {
-            return (MirrorDirection::Vertical, position);
-        }
-        if let Some(position) = self.get_horizontal_mirror_position() {
-            return (MirrorDirection::Horizontal, position);
-        }
-        panic!("No mirror found");
+    fn reflections_in(&self, direction: MirrorDirection, smudges: u32) -> Vec<Reflection> {
+        let lines = match direction {
+            MirrorDirection::Horizontal => &self.rows,
+            MirrorDirection::Vertical => &self.columns,
+        };
+
+        find_reflections(lines, smudges).into_iter()
+            .map(|(position, different_bits)| {
+                let smudges = different_bits.into_iter()
+                    .map(|(line, bit)| match direction {
+                        MirrorDirection::Horizontal => (bit, line),
+                        MirrorDirection::Vertical => (line, bit),
+                    })
+                    .collect();
+
+                Reflection { direction, position, smudges }
+            })
+            .collect()
     }
 
-    fn get_mirror_position_with_differences(&self) -> (MirrorDirection, usize) {
-        if let Some(position) = self.get_vertical_mirror_with_smudge_position() {
-            return (MirrorDirection::Vertical, position);
-        }
-        if let Some(position) = self.get_horizontal_mirror_with_smudge_position() {
-            return (MirrorDirection::Horizontal, position);
-        }
-        panic!("No mirror found");
+    // Every reflection line that needs exactly `smudges` tiles fixed, vertical lines first.
+    fn reflections(&self, smudges: u32) -> Vec<Reflection> {
+        let mut reflections = self.reflections_in(MirrorDirection::Vertical, smudges);
+        reflections.extend(self.reflections_in(MirrorDirection::Horizontal, smudges));
+        reflections
     }
 
-    fn get_vertical_mirror_with_smudge_position(&self) -> Option<usize> {
-        self.generic_get_mirror_position_with_differences(Map::get_columns_difference, self.width())
+    fn get_mirror_position(&self, smudges: u32) -> Reflection {
+        self.reflections(smudges).into_iter().next().expect("No mirror found")
     }
 
-    fn get_horizontal_mirror_with_smudge_position(&self) -> Option<usize> {
-        self.generic_get_mirror_position_with_differences(Map::get_rows_difference, self.height())
+    fn flip(&self, (x, y): (usize, usize)) -> Map {
+        let mut map = self.clone();
+        map.rows[y][x / 64] ^= 1 << (x % 64);
+        map.columns[x][y / 64] ^= 1 << (y % 64);
+        map
     }
-}
 
+    fn fix_smudge(&self) -> SmudgeFix {
+        let old_reflection = self.get_mirror_position(0);
+        let new_reflection = self.get_mirror_position(1);
+        let smudge = new_reflection.smudges[0];
+
+        SmudgeFix { smudge, old_reflection, new_reflection, corrected: self.flip(smudge) }
+    }
+
+    fn render(&self) -> String {
+        self.rows.iter()
+            .map(|row| (0..self.columns.len()).map(|x| if row[x / 64] & (1 << (x % 64)) != 0 { '#' } else { '.' }).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
 
 fn main() {
     let input = include_str!("input.txt");
     let maps = parse(input);
 
+    if std::env::args().nth(1).as_deref() == Some("smudges") {
+        print_smudges(&maps);
+        return;
+    }
+
     println!("Part 1: {:?}", part1(&maps));
     println!("Part 2: {:?}", part2(&maps));
 }
 
+fn print_smudges(maps: &[Map]) {
+    for (index, map) in maps.iter().enumerate() {
+        let fix = map.fix_smudge();
+
+        println!("Map {}: smudge at {:?}", index, fix.smudge);
+        println!("  old reflection: {:?} at {}", fix.old_reflection.direction, fix.old_reflection.position);
+        println!("  new reflection: {:?} at {}", fix.new_reflection.direction, fix.new_reflection.position);
+        println!("{}\n", fix.corrected.render());
+    }
+}
+
 fn part1(maps: &[Map]) -> usize {
-    maps.iter().map(|map| map.get_mirror_position())
-        .map(|(direction, position)| {
-            match direction {
-                MirrorDirection::Horizontal => 100 * position,
-                MirrorDirection::Vertical => position,
-            }
-        }).sum()
+    maps.iter().map(|map| map.get_mirror_position(0).summarize()).sum()
 }
 
 fn part2(maps: &[Map]) -> usize {
-    maps.iter().map(|map| map.get_mirror_position_with_differences())
-        .map(|(direction, position)| {
-            match direction {
-                MirrorDirection::Horizontal => 100 * position,
-                MirrorDirection::Vertical => position,
-            }
-        }).sum()
+    maps.iter().map(|map| map.fix_smudge().new_reflection.summarize()).sum()
 }
 
 fn parse_map(input: &str) -> Map {
@@ -186,16 +200,16 @@ fn parse_map(input: &str) -> Map {
         }).collect()
     }).collect();
 
-    Map { tiles }
+    Map::new(tiles)
 }
 
 fn parse(input: &str) -> Vec<Map> {
-    input.split("\r\n\r\n").map(|map| parse_map(map)).collect()
+    input.split("\r\n\r\n").map(parse_map).collect()
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{Map, parse_map, part1, part2};
+    use crate::{Map, MirrorDirection, Reflection, parse_map, part1, part2};
 
     #[test]
     fn test_vertical_reflection() {
@@ -210,7 +224,7 @@ mod tests {
 
         let map = parse_map(input);
 
-        assert_eq!(map.get_vertical_mirror_position(), Some(5));
+        assert_eq!(map.get_mirror_position(0), Reflection { direction: MirrorDirection::Vertical, position: 5, smudges: vec![] });
     }
 
     #[test]
@@ -226,14 +240,14 @@ mod tests {
 
         let map = parse_map(input);
 
-        assert_eq!(map.get_horizontal_mirror_position(), Some(4));
-        assert_eq!(map.get_vertical_mirror_position(), None);
-        assert_eq!(map.get_horizontal_mirror_with_smudge_position(), Some(1));
+        assert_eq!(map.reflections_in(MirrorDirection::Horizontal, 0), vec![Reflection { direction: MirrorDirection::Horizontal, position: 4, smudges: vec![] }]);
+        assert_eq!(map.reflections_in(MirrorDirection::Vertical, 0), vec![]);
+        assert_eq!(map.reflections_in(MirrorDirection::Horizontal, 1), vec![Reflection { direction: MirrorDirection::Horizontal, position: 1, smudges: vec![(4, 0)] }]);
     }
 
     #[test]
     fn test_full_example() {
-        let input = vec!(
+        let input = [
             r#"#.##..##.
                ..#.##.#.
                ##......#
@@ -247,7 +261,7 @@ mod tests {
                #####.##.
                #####.##.
                ..##..###
-               #....#..#"#);
+               #....#..#"#];
 
         let maps = input.iter().map(|map| parse_map(map)).collect::<Vec<Map>>();
 
@@ -256,7 +270,7 @@ mod tests {
 
     #[test]
     fn test_full_with_smudge_example() {
-        let input = vec!(
+        let input = [
             r#"#.##..##.
                ..#.##.#.
                ##......#
@@ -270,11 +284,65 @@ mod tests {
                #####.##.
                #####.##.
                ..##..###
-               #....#..#"#);
+               #....#..#"#];
 
         let maps = input.iter().map(|map| parse_map(map)).collect::<Vec<Map>>();
 
         assert_eq!(part2(&maps), 400);
     }
-}
 
+    #[test]
+    fn test_multiple_smudges() {
+        let map = parse_map(
+            r#"#.##..##.
+               ..#.##.#.
+               ##......#
+               ##......#
+               ..#.##.#.
+               ..##..##.
+               #.#.##.#."#);
+
+        assert_eq!(map.reflections(0).len(), 1);
+        assert_eq!(map.reflections(1), vec![Reflection { direction: MirrorDirection::Horizontal, position: 3, smudges: vec![(0, 0)] }]);
+
+        assert_eq!(map.reflections(2), vec![Reflection { direction: MirrorDirection::Vertical, position: 1, smudges: vec![(0, 0), (0, 6)] }]);
+    }
+
+    #[test]
+    fn test_fix_smudge() {
+        let map = parse_map(
+            r#"#.##..##.
+               ..#.##.#.
+               ##......#
+               ##......#
+               ..#.##.#.
+               ..##..##.
+               #.#.##.#."#);
+
+        let fix = map.fix_smudge();
+
+        assert_eq!(fix.smudge, (0, 0));
+        assert_eq!(fix.old_reflection, Reflection { direction: MirrorDirection::Vertical, position: 5, smudges: vec![] });
+        assert_eq!(fix.new_reflection, Reflection { direction: MirrorDirection::Horizontal, position: 3, smudges: vec![(0, 0)] });
+        assert_eq!(fix.corrected.reflections_in(MirrorDirection::Horizontal, 0)[0].position, 3);
+        assert_eq!(fix.corrected.flip(fix.smudge), map);
+        assert_eq!(fix.corrected.render(), "..##..##.\n..#.##.#.\n##......#\n##......#\n..#.##.#.\n..##..##.\n#.#.##.#.");
+    }
+
+    #[test]
+    fn test_maps_larger_than_a_word() {
+        // 150x70 tiles, mirrored after column 100
+        let input = (0..70).map(|y: usize| (0..150).map(|x: usize| {
+            let x = if x < 100 { x } else { 199 - x };
+            if (x * x * 7 + y * 13 + x * y) % 5 < 2 { '#' } else { '.' }
+        }).collect::<String>()).collect::<Vec<_>>().join("\n");
+        let map = parse_map(&input);
+
+        assert_eq!(map.get_mirror_position(0), Reflection { direction: MirrorDirection::Vertical, position: 100, smudges: vec![] });
+        assert_eq!(map.render(), input);
+
+        let smudged = map.flip((120, 65));
+        assert_eq!(smudged.reflections(1)[0], Reflection { direction: MirrorDirection::Vertical, position: 100, smudges: vec![(79, 65)] });
+        assert_eq!(smudged.flip((79, 65)).get_mirror_position(0).position, 100);
+    }
+}
@@ -0,0 +1,333 @@
+use std::fmt::{Debug, Formatter};
+
+use crate::{Direction, Map, Tile};
+
+// Bitboard version of the map. Every row is stored in `words_per_row` words where bit x is the tile at column x,
+// so rocks are moved a whole row (or a whole board) at a time instead of tile by tile.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Dish {
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    rounded: Vec<u64>,
+    cubes: Vec<u64>,
+}
+
+// The words of a row covering the tiles from `start` to `end` (excluded), each with the mask of those tiles in it.
+fn masks(start: usize, end: usize) -> impl Iterator<Item=(usize, u64)> {
+    (start / 64..end.div_ceil(64)).map(move |word| {
+        let from = start.max(word * 64) - word * 64;
+        let to = end.min(word * 64 + 64) - word * 64;
+        let mask = if to - from == 64 { u64::MAX } else { ((1 << (to - from)) - 1) << from };
+        (word, mask)
+    })
+}
+
+// Transposes a 64x64 block of bits, where bit x of word y is the bit at column x and row y, by swapping
+// the off-diagonal 32x32 blocks, then the 16x16 blocks inside them, and so on.
+fn transpose_block(block: &mut [u64; 64]) {
+    let mut size = 32;
+    let mut mask: u64 = 0x0000_0000_FFFF_FFFF;
+
+    while size != 0 {
+        let mut k = 0;
+        while k < 64 {
+            let swapped = ((block[k] >> size) ^ block[k + size]) & mask;
+            block[k] ^= swapped << size;
+            block[k + size] ^= swapped;
+            k = (k + size + 1) & !size;
+        }
+        size >>= 1;
+        mask ^= mask << size;
+    }
+}
+
+impl Dish {
+    pub(crate) fn from_map(map: &Map) -> Dish {
+        let words_per_row = map.width.div_ceil(64).max(1);
+        let mut rounded = vec![0; words_per_row * map.height];
+        let mut cubes = vec![0; words_per_row * map.height];
+
+        for (y, row) in map.tiles.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                let (word, bit) = (y * words_per_row + x / 64, 1 << (x % 64));
+                match tile {
+                    Tile::RoundedRock => rounded[word] |= bit,
+                    Tile::CubedRock => cubes[word] |= bit,
+                    Tile::Empty => {}
+                }
+            }
+        }
+
+        Dish { width: map.width, height: map.height, words_per_row, rounded, cubes }
+    }
+
+    fn get_tile(&self, x: usize, y: usize) -> Tile {
+        let (word, bit) = (y * self.words_per_row + x / 64, 1 << (x % 64));
+        if self.rounded[word] & bit != 0 {
+            Tile::RoundedRock
+        } else if self.cubes[word] & bit != 0 {
+            Tile::CubedRock
+        } else {
+            Tile::Empty
+        }
+    }
+
+    pub(crate) fn tilt(&mut self, direction: Direction) {
+        match direction {
+            // the columns of the dish are the rows of its transpose
+            Direction::North | Direction::South => {
+                let mut transposed = self.transposed();
+                transposed.tilt_rows(direction == Direction::North);
+                *self = transposed.transposed();
+            }
+            Direction::West | Direction::East => self.tilt_rows(direction == Direction::West),
+        }
+    }
+
+    // The dish flipped over its diagonal, 64x64 blocks at a time.
+    fn transposed(&self) -> Dish {
+        let words_per_row = self.height.div_ceil(64).max(1);
+        let mut transposed = Dish {
+            width: self.height,
+            height: self.width,
+            words_per_row,
+            rounded: vec![0; words_per_row * self.width],
+            cubes: vec![0; words_per_row * self.width],
+        };
+
+        for (words, transposed_words) in [(&self.rounded, &mut transposed.rounded), (&self.cubes, &mut transposed.cubes)] {
+            for block_y in 0..words_per_row {
+                for block_x in 0..self.words_per_row {
+                    let mut block = [0; 64];
+                    for (y, word) in block.iter_mut().enumerate().take(self.height.saturating_sub(block_y * 64)) {
+                        *word = words[(block_y * 64 + y) * self.words_per_row + block_x];
+                    }
+
+                    transpose_block(&mut block);
+
+                    for (x, &word) in block.iter().enumerate().take(self.width.saturating_sub(block_x * 64)) {
+                        transposed_words[(block_x * 64 + x) * words_per_row + block_y] = word;
+                    }
+                }
+            }
+        }
+
+        transposed
+    }
+
+    // Every stretch of a row between two cubes ends up with its rocks packed against one of its edges, so the
+    // rocks of a stretch are counted and written back with masks, a word at a time.
+    fn tilt_rows(&mut self, west: bool) {
+        for (rounded, cubes) in self.rounded.chunks_mut(self.words_per_row).zip(self.cubes.chunks(self.words_per_row)) {
+            let cube_positions = cubes.iter().enumerate().flat_map(|(index, &word)| set_bits(word).map(move |bit| index * 64 + bit));
+
+            let mut start = 0;
+            for end in cube_positions.chain([self.width]) {
+                if end > start {
+                    let rocks = masks(start, end).map(|(word, mask)| (rounded[word] & mask).count_ones() as usize).sum::<usize>();
+                    for (word, mask) in masks(start, end) {
+                        rounded[word] &= !mask;
+                    }
+
+                    let packed_start = if west { start } else { end - rocks };
+                    for (word, mask) in masks(packed_start, packed_start + rocks) {
+                        rounded[word] |= mask;
+                    }
+                }
+                start = end + 1;
+            }
+        }
+    }
+
+    pub(crate) fn run(&mut self, program: &[Direction]) {
+        for &direction in program {
+            self.tilt(direction);
+        }
+    }
+
+    // Total load on the support beam at the `beam` edge of the dish: each rock weighs its distance to the opposite edge.
+    pub(crate) fn load(&self, beam: Direction) -> usize {
+        match beam {
+            Direction::North | Direction::South => self.rounded.chunks(self.words_per_row)
+                .enumerate()
+                .map(|(y, row)| {
+                    let rocks = row.iter().map(|word| word.count_ones() as usize).sum::<usize>();
+                    let weight = if beam == Direction::North { self.height - y } else { y + 1 };
+                    rocks * weight
+                })
+                .sum(),
+            Direction::West | Direction::East => self.rounded.chunks(self.words_per_row)
+                .flat_map(|row| row.iter().enumerate().flat_map(|(index, &word)| set_bits(word).map(move |bit| index * 64 + bit)))
+                .map(|x| if beam == Direction::West { self.width - x } else { x + 1 })
+                .sum(),
+        }
+    }
+}
+
+fn set_bits(mut word: u64) -> impl Iterator<Item=usize> {
+    std::iter::from_fn(move || {
+        if word == 0 {
+            return None;
+        }
+        let bit = word.trailing_zeros() as usize;
+        word &= word - 1;
+        Some(bit)
+    })
+}
+
+impl Debug for Dish {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                write!(f, "{:?}", self.get_tile(x, y))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_input;
+
+    fn dish(input: &str) -> Dish {
+        Dish::from_map(&parse_input(input))
+    }
+
+    #[test]
+    fn test_tilt_each_direction() {
+        let mut north = dish("O.#\n.O.\nOO.");
+        north.tilt(Direction::North);
+        assert_eq!(north, dish("OO#\nOO.\n..."));
+
+        let mut south = dish("O.#\n.O.\nOO.");
+        south.tilt(Direction::South);
+        assert_eq!(south, dish("..#\nOO.\nOO."));
+
+        let mut west = dish(".O#.O\nO..O.");
+        west.tilt(Direction::West);
+        assert_eq!(west, dish("O.#O.\nOO..."));
+
+        let mut east = dish(".O#.O\nO..O.");
+        east.tilt(Direction::East);
+        assert_eq!(east, dish(".O#.O\n...OO"));
+    }
+
+    #[test]
+    fn test_load_on_each_beam() {
+        let dish = dish("O..\n..O\n.#.");
+
+        assert_eq!(dish.load(Direction::North), 3 + 2);
+        assert_eq!(dish.load(Direction::South), 1 + 2);
+        assert_eq!(dish.load(Direction::West), 3 + 1);
+        assert_eq!(dish.load(Direction::East), 1 + 3);
+    }
+
+    #[test]
+    fn test_wide_rows() {
+        let row = format!("{}O.O#{}O", ".".repeat(70), ".".repeat(60));
+        let mut wide = dish(&row);
+
+        wide.tilt(Direction::West);
+        assert_eq!(wide, dish(&format!("OO{}#O{}", ".".repeat(71), ".".repeat(60))));
+
+        wide.tilt(Direction::East);
+        assert_eq!(wide, dish(&format!("{}OO#{}O", ".".repeat(71), ".".repeat(60))));
+    }
+
+    #[test]
+    fn test_transposed() {
+        // several blocks in both directions, with partial blocks on the right and at the bottom
+        let input: Vec<String> = (0..150).map(|y| (0..70).map(|x| ['.', 'O', '#'][(x * 7 + y * 3 + x * y) % 3]).collect()).collect();
+        let dish = dish(&input.join("\n"));
+        let transposed = dish.transposed();
+
+        assert_eq!((transposed.width, transposed.height), (150, 70));
+        for y in 0..150 {
+            for x in 0..70 {
+                assert_eq!(transposed.get_tile(y, x), dish.get_tile(x, y));
+            }
+        }
+        assert_eq!(transposed.transposed(), dish);
+    }
+
+    // Moves rocks one tile at a time until none can move, as a plain reference for the word-wise tilt.
+    fn reference_tilt(map: &mut Map, direction: Direction) {
+        let (dx, dy): (isize, isize) = match direction {
+            Direction::North => (0, -1),
+            Direction::West => (-1, 0),
+            Direction::South => (0, 1),
+            Direction::East => (1, 0),
+        };
+        let mut moved = true;
+        while moved {
+            moved = false;
+            for y in 0..map.height {
+                for x in 0..map.width {
+                    let (to_x, to_y) = (x.wrapping_add_signed(dx), y.wrapping_add_signed(dy));
+                    if to_x < map.width && to_y < map.height
+                        && map.tiles[y][x] == Tile::RoundedRock && map.tiles[to_y][to_x] == Tile::Empty {
+                        map.tiles[to_y][to_x] = Tile::RoundedRock;
+                        map.tiles[y][x] = Tile::Empty;
+                        moved = true;
+                    }
+                }
+            }
+        }
+    }
+
+    fn reference_north_load(map: &Map) -> usize {
+        map.tiles.iter().enumerate()
+            .map(|(y, row)| row.iter().filter(|&&tile| tile == Tile::RoundedRock).count() * (map.height - y))
+            .sum()
+    }
+
+    #[test]
+    fn test_matches_reference_tilt() {
+        // several words per row and several blocks per column, with partial ones on the right and at the bottom
+        let input: Vec<String> = (0..150).map(|y| (0..200).map(|x| match (x * 31 + y * 17 + x * y) % 7 {
+            0 => '#',
+            1..=3 => 'O',
+            _ => '.',
+        }).collect()).collect();
+        let mut map = parse_input(&input.join("\n"));
+        let mut dish = Dish::from_map(&map);
+
+        for _ in 0..2 {
+            for direction in [Direction::North, Direction::West, Direction::South, Direction::East] {
+                reference_tilt(&mut map, direction);
+                dish.tilt(direction);
+                assert_eq!(dish, Dish::from_map(&map), "after tilting {direction:?}");
+                assert_eq!(dish.load(Direction::North), reference_north_load(&map));
+            }
+        }
+    }
+
+    // Tilting moves every rock of a stretch at once, so this dish that would take a sweep per tile a rock moves
+    // is still tilted in a fraction of a second in release builds.
+    #[test]
+    #[ignore]
+    fn test_large_dish() {
+        let size = 4096;
+        let words_per_row = size / 64;
+
+        // a rock at the east end of every row and at the south end of every column
+        let mut rounded = vec![0; words_per_row * size];
+        for y in 0..size {
+            rounded[y * words_per_row + words_per_row - 1] |= 1 << 63;
+        }
+        rounded[(size - 1) * words_per_row..].fill(u64::MAX);
+        let mut dish = Dish { width: size, height: size, words_per_row, rounded, cubes: vec![0; words_per_row * size] };
+        let rocks = dish.rounded.iter().map(|word| word.count_ones()).sum::<u32>();
+
+        dish.run(&[Direction::North, Direction::West, Direction::South, Direction::East]);
+
+        // rocks rest in the south east corner, in full rows
+        assert_eq!(dish.rounded.iter().map(|word| word.count_ones()).sum::<u32>(), rocks);
+        assert_eq!(dish.get_tile(size - 1, size - 1), Tile::RoundedRock);
+        assert_eq!(dish.get_tile(0, size - 2), Tile::Empty);
+    }
+}
@@ -1,6 +1,9 @@
-use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 
+use crate::dish::Dish;
+
+mod dish;
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 enum Tile {
     RoundedRock,
@@ -37,16 +40,6 @@ impl Debug for Map {
     }
 }
 
-impl Map {
-    fn get_tile(&self, pos: (usize, usize)) -> Option<Tile> {
-        let (x, y) = pos;
-        if x >= self.width || y >= self.height {
-            return None;
-        }
-        Some(self.tiles[y][x])
-    }
-}
-
 // Only works for part 1
 fn get_total_north_roll_load(map: &Map) -> usize {
     let mut total_load = 0;
@@ -81,6 +74,7 @@ fn get_sum_between(start: usize, end: usize) -> usize {
     (start + end) * n / 2
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum Direction {
     North,
     West,
@@ -88,55 +82,6 @@ enum Direction {
     East,
 }
 
-impl Direction {
-    fn apply_offset(&self, pos: (usize, usize)) -> Option<(usize, usize)> {
-        let (x, y) = pos;
-        Some(match self {
-            Direction::North => (x, y.checked_sub(1)?),
-            Direction::West => (x.checked_sub(1)?, y),
-            Direction::South => (x, y + 1),
-            Direction::East => (x + 1, y),
-        })
-    }
-}
-
-fn cycle(map: &mut Map) {
-    roll_map(map, Direction::North);
-    roll_map(map, Direction::West);
-    roll_map(map, Direction::South);
-    roll_map(map, Direction::East);
-}
-
-fn roll_map(map: &mut Map, direction: Direction) {
-    for x in 0..map.width {
-        for y in 0..map.height {
-            let mut current = (x, y);
-
-            let mut target = current;
-
-            while map.get_tile(current) == Some(Tile::Empty) ||
-                map.get_tile(current) == Some(Tile::RoundedRock) {
-                let option = direction.apply_offset(current);
-                if let Some(option) = option {
-                    current = option;
-                } else {
-                    break;
-                }
-
-                if map.get_tile(current) == Some(Tile::Empty) {
-                    target = current;
-                }
-            }
-
-            if target != (x, y) {
-                let tile = map.tiles[y][x];
-                map.tiles[y][x] = map.tiles[target.1][target.0];
-                map.tiles[target.1][target.0] = tile;
-            }
-        }
-    }
-}
-
 fn parse_input(input: &str) -> Map {
     let tiles: Vec<Vec<Tile>> = input.lines().map(|line| line.chars().map(|c| match c {
         'O' => Tile::RoundedRock,
@@ -145,65 +90,102 @@ fn parse_input(input: &str) -> Map {
         _ => panic!("Unknown tile: {}", c),
     }).collect()).collect();
 
-    let width = tiles.get(0).map(|row| row.len()).unwrap_or(0);
+    let width = tiles.first().map(|row| row.len()).unwrap_or(0);
     let height = tiles.len();
 
     Map { tiles, width, height }
 }
 
-fn get_total_north_load(map: &Map) -> usize {
-    let mut answer = 0;
-
-    for y in 0..map.height {
-        for x in 0..map.width {
-            if map.tiles[y][x] == Tile::RoundedRock {
-                answer += map.height - y;
-            }
-        }
-    }
+// The spin cycle from part 2.
+const SPIN_CYCLE: [Direction; 4] = [Direction::North, Direction::West, Direction::South, Direction::East];
+
+// Parses a tilt program such as "NNE SW", ignoring whitespace.
+fn parse_program(input: &str) -> Result<Vec<Direction>, char> {
+    input.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c.to_ascii_uppercase() {
+            'N' => Ok(Direction::North),
+            'W' => Ok(Direction::West),
+            'S' => Ok(Direction::South),
+            'E' => Ok(Direction::East),
+            _ => Err(c),
+        })
+        .collect()
+}
 
-    answer
+// Runs `program` `repetitions` times, skipping ahead once the dish starts repeating.
+fn run_program(map: &Map, program: &[Direction], repetitions: usize) -> Dish {
+    cycle_detection::state_after(Dish::from_map(map), repetitions, |dish| {
+        let mut dish = dish.clone();
+        dish.run(program);
+        dish
+    })
 }
 
 fn part2(map: &Map) -> usize {
-    let mut map = map.clone();
+    run_program(map, &SPIN_CYCLE, 1_000_000_000).load(Direction::North)
+}
+
+fn main() {
+    let input = include_str!("input.txt");
+    let map = parse_input(input);
 
-    let mut cache = HashMap::new();
-    cache.insert(map.clone(), 0);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        [] => {
+            let part1 = get_total_north_roll_load(&map);
+            println!("Part 1: {}", part1);
 
-    let mut loop_len = 0;
-    let mut start_of_loop = 0;
+            let part2 = part2(&map);
+            println!("Part 2: {}", part2);
+        }
+        ["program", program, repetitions] => {
+            let program = parse_program(program).unwrap_or_else(|c| panic!("Unknown tilt direction: {}", c));
+            let repetitions = repetitions.parse().expect("repetitions must be a number");
 
-    for current_index in 0..1_000_000_000 {
-        cycle(&mut map);
-        if let Some(new_start_of_loop) = cache.insert(map.clone(), current_index + 1) {
-            start_of_loop = new_start_of_loop;
-            loop_len = current_index - new_start_of_loop + 1;
-            println!("Loop found from {} to {}", start_of_loop, start_of_loop + loop_len);
-            break;
+            let dish = run_program(&map, &program, repetitions);
+            for beam in [Direction::North, Direction::West, Direction::South, Direction::East] {
+                println!("{:?} load: {}", beam, dish.load(beam));
+            }
         }
+        _ => eprintln!("Usage: day-14 [program <tilts, e.g. \"NNE SW\"> <repetitions>]"),
     }
+}
+
+#[cfg(test)]
+mod tests {
+    const EXAMPLE: &str = r#"O....#....
+O.OO#....#
+.....##...
+OO.#O....O
+.O.....O#.
+O.#..O.#.#
+..O..#O..O
+.......O..
+#....###..
+#OO..#...."#;
 
-    for _ in 0..(1_000_000_000 - start_of_loop) % loop_len {
-        cycle(&mut map);
+    #[test]
+    fn test_part2() {
+        let map = super::parse_input(EXAMPLE);
+        assert_eq!(super::part2(&map), 64);
     }
 
-    get_total_north_load(&map)
-}
+    #[test]
+    fn test_programs() {
+        use super::Direction::*;
 
-fn main() {
-    let input = include_str!("input.txt");
-    let map = parse_input(input);
+        let map = super::parse_input(EXAMPLE);
 
-    let part1 = get_total_north_roll_load(&map);
-    println!("Part 1: {}", part1);
+        assert_eq!(super::parse_program("NNE sw"), Ok(vec![North, North, East, South, West]));
+        assert_eq!(super::parse_program("NX"), Err('X'));
 
-    let part2 = part2(&mut map.clone());
-    println!("Part 2: {}", part2);
-}
+        // a single north tilt is part 1
+        assert_eq!(super::run_program(&map, &[North], 1).load(North), 136);
+        assert_eq!(super::run_program(&map, &[North], 1_000_000_000).load(North), 136);
+        assert_eq!(super::run_program(&map, &super::SPIN_CYCLE, 3), super::run_program(&map, &super::parse_program("NWSE NWSE NWSE").unwrap(), 1));
+    }
 
-#[cfg(test)]
-mod tests {
     #[test]
     fn test_get_sum_between() {
         let result = super::get_sum_between(10, 10);
@@ -227,7 +209,7 @@ O..#.OO...
 
     #[test]
     fn roll_north() {
-        let mut map = super::parse_input(r#"O....#....
+        let map = super::parse_input(r#"O....#....
 O.OO#....#
 .....##...
 OO.#O....O
@@ -237,8 +219,9 @@ O.#..O.#.#
 .......O..
 #....###..
 #OO..#...."#);
-        super::roll_map(&mut map, super::Direction::North);
-        assert_eq!(map, super::parse_input(r#"OOOO.#.O..
+        let mut dish = super::Dish::from_map(&map);
+        dish.tilt(super::Direction::North);
+        assert_eq!(dish, super::Dish::from_map(&super::parse_input(r#"OOOO.#.O..
 OO..#....#
 OO..O##..O
 O..#.OO...
@@ -247,6 +230,6 @@ O..#.OO...
 ..O..#.O.O
 ..O.......
 #....###..
-#....#...."#));
+#....#...."#)));
     }
 }
\ No newline at end of file
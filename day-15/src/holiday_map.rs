@@ -0,0 +1,298 @@
+// The Holiday ASCII String Helper Manual Arrangement Procedure as a collection: a fixed number of boxes,
+// each one keeping its entries in insertion order.
+
+use std::borrow::Borrow;
+use std::mem;
+
+use crate::get_hash_value;
+
+pub const DEFAULT_BOXES: usize = 256;
+
+// Picks the box a key goes into. The result is taken modulo the number of boxes.
+pub trait BoxHasher<K: ?Sized> {
+    fn hash(&self, key: &K) -> usize;
+}
+
+// The HASH algorithm from the puzzle.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HolidayHasher;
+
+impl<K: AsRef<str> + ?Sized> BoxHasher<K> for HolidayHasher {
+    fn hash(&self, key: &K) -> usize {
+        get_hash_value(key.as_ref()) as usize
+    }
+}
+
+impl<K: ?Sized, F: Fn(&K) -> usize> BoxHasher<K> for F {
+    fn hash(&self, key: &K) -> usize {
+        self(key)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HolidayMap<K, V, H = HolidayHasher> {
+    boxes: Vec<Vec<(K, V)>>,
+    hasher: H,
+    len: usize,
+}
+
+impl<K, V, H: Default> HolidayMap<K, V, H> {
+    pub fn new() -> Self {
+        Self::with_hasher(H::default(), DEFAULT_BOXES)
+    }
+}
+
+impl<K, V, H: Default> Default for HolidayMap<K, V, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, H> HolidayMap<K, V, H> {
+    pub fn with_hasher(hasher: H, boxes: usize) -> Self {
+        assert!(boxes > 0, "a holiday map needs at least one box");
+
+        let boxes = (0..boxes).map(|_| Vec::new()).collect();
+        HolidayMap { boxes, hasher, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn box_index<Q: ?Sized>(&self, key: &Q) -> usize where H: BoxHasher<Q> {
+        self.hasher.hash(key) % self.boxes.len()
+    }
+
+    // Entries in box order, and in slot order inside each box.
+    pub fn iter(&self) -> impl Iterator<Item=(&K, &V)> {
+        self.boxes.iter().flatten().map(|(key, value)| (key, value))
+    }
+
+    // Every box with its entries in slot order, empty boxes included.
+    pub fn boxes(&self) -> impl Iterator<Item=&[(K, V)]> {
+        self.boxes.iter().map(Vec::as_slice)
+    }
+}
+
+impl<K: Eq, V, H: BoxHasher<K>> HolidayMap<K, V, H> {
+    // Replaces the value in place when the key is already present, otherwise appends it to the back of its box.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.entry(key) {
+            Entry::Occupied(entry) => Some(mem::replace(entry.value, value)),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                None
+            }
+        }
+    }
+
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        let index = self.box_index(&key);
+        let lenses = &mut self.boxes[index];
+
+        match lenses.iter().position(|(k, _)| k == &key) {
+            Some(slot) => {
+                let (key, value) = &mut lenses[slot];
+                Entry::Occupied(OccupiedEntry { key, value })
+            }
+            None => Entry::Vacant(VacantEntry { key, lenses, len: &mut self.len }),
+        }
+    }
+}
+
+impl<K, V, H> HolidayMap<K, V, H> {
+    pub fn get<Q: Eq + ?Sized>(&self, key: &Q) -> Option<&V> where K: Borrow<Q>, H: BoxHasher<Q> {
+        self.boxes[self.box_index(key)].iter()
+            .find(|(k, _)| k.borrow() == key)
+            .map(|(_, value)| value)
+    }
+
+    pub fn get_mut<Q: Eq + ?Sized>(&mut self, key: &Q) -> Option<&mut V> where K: Borrow<Q>, H: BoxHasher<Q> {
+        let index = self.box_index(key);
+        self.boxes[index].iter_mut()
+            .find(|(k, _)| k.borrow() == key)
+            .map(|(_, value)| value)
+    }
+
+    pub fn contains_key<Q: Eq + ?Sized>(&self, key: &Q) -> bool where K: Borrow<Q>, H: BoxHasher<Q> {
+        self.get(key).is_some()
+    }
+
+    // Removes the entry and moves the entries behind it one slot forward.
+    pub fn remove<Q: Eq + ?Sized>(&mut self, key: &Q) -> Option<V> where K: Borrow<Q>, H: BoxHasher<Q> {
+        let index = self.box_index(key);
+        let lenses = &mut self.boxes[index];
+        let slot = lenses.iter().position(|(k, _)| k.borrow() == key)?;
+
+        self.len -= 1;
+        Some(lenses.remove(slot).1)
+    }
+}
+
+pub enum Entry<'a, K, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+pub struct OccupiedEntry<'a, K, V> {
+    key: &'a K,
+    value: &'a mut V,
+}
+
+pub struct VacantEntry<'a, K, V> {
+    key: K,
+    lenses: &'a mut Vec<(K, V)>,
+    len: &'a mut usize,
+}
+
+impl<'a, K, V> VacantEntry<'a, K, V> {
+    pub fn insert(self, value: V) -> &'a mut V {
+        *self.len += 1;
+        self.lenses.push((self.key, value));
+        &mut self.lenses.last_mut().expect("an entry was just pushed").1
+    }
+}
+
+impl<'a, K, V> Entry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key,
+            Entry::Vacant(entry) => &entry.key,
+        }
+    }
+
+    pub fn and_modify(self, f: impl FnOnce(&mut V)) -> Self {
+        match self {
+            Entry::Occupied(entry) => {
+                f(entry.value);
+                Entry::Occupied(entry)
+            }
+            vacant => vacant,
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.value,
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_example_boxes() {
+        let mut map: HolidayMap<&str, u64> = HolidayMap::new();
+        map.insert("rn", 1);
+        map.insert("cm", 2);
+        map.insert("qp", 3);
+        map.insert("pc", 4);
+        map.remove("qp");
+        map.insert("ot", 9);
+        map.insert("ab", 5);
+        map.remove("pc");
+        map.insert("pc", 6);
+        map.insert("ot", 7);
+
+        let boxes: Vec<_> = map.boxes().enumerate().filter(|(_, lenses)| !lenses.is_empty()).collect();
+        assert_eq!(boxes, vec![
+            (0, &[("rn", 1), ("cm", 2)][..]),
+            (3, &[("ot", 7), ("ab", 5), ("pc", 6)][..]),
+        ]);
+        assert_eq!(map.len(), 5);
+        assert_eq!(map.get("ot"), Some(&7));
+        assert_eq!(map.get("qp"), None);
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut map: HolidayMap<String, u64> = HolidayMap::new();
+
+        *map.entry("rn".to_string()).or_insert(0) += 1;
+        map.entry("rn".to_string()).and_modify(|value| *value *= 10).or_insert(0);
+        map.entry("cm".to_string()).and_modify(|value| *value *= 10).or_insert_with(|| 3);
+
+        assert_eq!(map.get("rn"), Some(&10));
+        assert_eq!(map.get("cm"), Some(&3));
+        assert_eq!(map.entry("cm".to_string()).key(), "cm");
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn test_custom_hasher() {
+        let mut map = HolidayMap::with_hasher(|key: &u32| *key as usize, 4);
+        for key in 0..10 {
+            map.insert(key, key * 2);
+        }
+
+        assert_eq!(map.boxes().map(<[_]>::len).collect::<Vec<_>>(), vec![3, 3, 2, 2]);
+        assert_eq!(map.iter().map(|(key, _)| *key).collect::<Vec<_>>(), vec![0, 4, 8, 1, 5, 9, 2, 6, 3, 7]);
+        assert_eq!(map.remove(&5), Some(10));
+        assert!(!map.contains_key(&5));
+    }
+
+    #[test]
+    fn test_matches_std_hash_map() {
+        const KEYS: [&str; 12] = ["rn", "cm", "qp", "pc", "ot", "ab", "a", "b", "hash", "map", "lens", "box"];
+
+        let mut rng = StdRng::seed_from_u64(15);
+
+        for _ in 0..50 {
+            let mut map: HolidayMap<&str, u32> = HolidayMap::new();
+            let mut expected = HashMap::new();
+
+            for _ in 0..200 {
+                let key = KEYS[rng.gen_range(0..KEYS.len())];
+                match rng.gen_range(0..4) {
+                    0 => assert_eq!(map.remove(key), expected.remove(key)),
+                    1 => {
+                        *map.entry(key).or_insert(0) += 1;
+                        *expected.entry(key).or_insert(0) += 1;
+                    }
+                    2 => {
+                        if let Some(value) = map.get_mut(key) {
+                            *value += 100;
+                        }
+                        if let Some(value) = expected.get_mut(key) {
+                            *value += 100;
+                        }
+                    }
+                    _ => {
+                        let value = rng.gen();
+                        assert_eq!(map.insert(key, value), expected.insert(key, value));
+                    }
+                }
+
+                assert_eq!(map.len(), expected.len());
+                assert_eq!(map.is_empty(), expected.is_empty());
+                assert_eq!(map.get(key), expected.get(key));
+            }
+
+            let mut entries: Vec<_> = map.iter().map(|(&key, &value)| (key, value)).collect();
+            let boxes: Vec<_> = entries.iter().map(|(key, _)| get_hash_value(key)).collect();
+            assert!(boxes.windows(2).all(|pair| pair[0] <= pair[1]));
+
+            let mut expected_entries: Vec<_> = expected.into_iter().collect();
+            entries.sort();
+            expected_entries.sort();
+            assert_eq!(entries, expected_entries);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::holiday_map::HolidayMap;
+
+pub mod holiday_map;
+pub mod trace;
+
+pub fn get_hash_value(input: &str) -> u64 {
+    input.chars()
+        .fold(0, |acc, c| (acc + c as u64) * 17 % 256)
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Operation<'a> {
+    Insert(&'a str, u64),
+    Remove(&'a str),
+}
+
+pub fn parse_operation(part: &str) -> Operation<'_> {
+    if let Some((lens, value)) = part.split_once('=') {
+        Operation::Insert(lens, value.parse().expect("string after = is number"))
+    } else if let Some((lens, "")) = part.split_once('-') {
+        Operation::Remove(lens)
+    } else {
+        panic!("Invalid part: {}", part);
+    }
+}
+
+pub fn apply_operation<'a>(boxes: &mut HolidayMap<&'a str, u64>, operation: &Operation<'a>) {
+    match *operation {
+        Operation::Insert(lens, focal_length) => {
+            boxes.insert(lens, focal_length);
+        }
+        Operation::Remove(lens) => {
+            boxes.remove(lens);
+        }
+    }
+}
+
+pub fn focusing_power(boxes: &HolidayMap<&str, u64>) -> u64 {
+    boxes.boxes().enumerate().map(|(k, lens)| {
+        let box_pos = (k + 1) as u64;
+        lens.iter().enumerate().map(|(slot, (_, focal_length))| {
+            let slot = slot as u64 + 1;
+            box_pos * slot * focal_length
+        }).sum::<u64>()
+    }).sum()
+}
+
+pub fn part2(input: &str) -> u64 {
+    let mut boxes = HolidayMap::new();
+
+    for part in input.split(',') {
+        apply_operation(&mut boxes, &parse_operation(part));
+    }
+
+    focusing_power(&boxes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7";
+
+    #[test]
+    fn test_example() {
+        assert_eq!(get_hash_value("HASH"), 52);
+        assert_eq!(EXAMPLE.split(',').map(get_hash_value).sum::<u64>(), 1320);
+        assert_eq!(part2(EXAMPLE), 145);
+    }
+}
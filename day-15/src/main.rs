@@ -1,45 +1,33 @@
-fn get_hash_value(input: &str) -> u64 {
-    input.chars()
-        .fold(0, |acc, c| (acc + c as u64) * 17 % 256)
-}
-
-fn part2(input: &str) -> u64 {
-    let mut hashmap: Vec<Vec<(&str, u64)>> = vec![vec![]; 256];
+use day_15::trace::{largest_focusing_power_changes, trace};
+use day_15::{get_hash_value, part2};
 
-    for part in input.split(',') {
-        if let Some((lens, value)) = part.split_once('=') {
-            let hash = get_hash_value(lens) as usize;
-            let vec = &mut hashmap[hash];
-            let value = value.parse().expect("string after = is number");
+fn print_trace(input: &str, steps: usize) {
+    let steps: Vec<_> = trace(input).take(steps).collect();
 
-            if let Some((_, v)) = vec.iter_mut().find(|(l, _)| l == &lens) {
-                *v = value;
-            } else {
-                vec.push((lens, value));
-            }
-        } else if let Some((lens, "")) = part.split_once('-') {
-            let hash = get_hash_value(lens) as usize;
-            hashmap[hash].retain(|(l, _)| l != &lens);
-        } else {
-            panic!("Invalid part: {}", part);
-        }
+    for step in &steps {
+        println!("{}", step);
     }
 
-    hashmap.iter().enumerate().map(|(k, lens)| {
-        let box_pos = (k + 1) as u64;
-        lens.iter().enumerate().map(|(slot, (_, focal_length))| {
-            let slot = slot as u64 + 1;
-            box_pos * slot * focal_length
-        }).sum::<u64>()
-    }).sum()
+    println!("Largest focusing power changes:");
+    for step in largest_focusing_power_changes(&steps, 5) {
+        println!("  step {} \"{}\": {:+} (now {})", step.step, step.instruction, step.focusing_power_change, step.focusing_power);
+    }
 }
 
 fn main() {
     let input = include_str!("input.txt");
 
-    let part1: u64 = input.split(',').map(get_hash_value).sum();
-    println!("Part 1: {}", part1);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        [] => {
+            let part1: u64 = input.split(',').map(get_hash_value).sum();
+            println!("Part 1: {}", part1);
 
-    let part2 = part2(input);
-    println!("Part 2: {}", part2);
+            let part2 = part2(input);
+            println!("Part 2: {}", part2);
+        }
+        ["trace"] => print_trace(input, usize::MAX),
+        ["trace", steps] => print_trace(input, steps.parse().expect("steps must be a number")),
+        _ => eprintln!("Usage: day-15 [trace [steps]]"),
+    }
 }
@@ -0,0 +1,115 @@
+// Step by step execution of the initialization sequence, showing the boxes the same way the puzzle does.
+
+use std::fmt::{Display, Formatter};
+use std::str::Split;
+
+use crate::holiday_map::HolidayMap;
+use crate::{apply_operation, focusing_power, parse_operation};
+
+pub struct TraceStep<'a> {
+    pub step: usize,
+    pub instruction: &'a str,
+    pub boxes: String,
+    pub focusing_power: u64,
+    pub focusing_power_change: i64,
+}
+
+impl Display for TraceStep<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "After \"{}\":", self.instruction)?;
+        write!(f, "{}", self.boxes)
+    }
+}
+
+// Lazily runs one instruction per item, so stopping at step N is `trace(input).take(N)`.
+pub struct Trace<'a> {
+    instructions: Split<'a, char>,
+    boxes: HolidayMap<&'a str, u64>,
+    step: usize,
+    focusing_power: u64,
+}
+
+pub fn trace(input: &str) -> Trace<'_> {
+    Trace { instructions: input.split(','), boxes: HolidayMap::new(), step: 0, focusing_power: 0 }
+}
+
+// Lines like "Box 3: [ot 7] [ab 5] [pc 6]" for every box that has lenses.
+pub fn format_boxes(boxes: &HolidayMap<&str, u64>) -> String {
+    boxes.boxes()
+        .enumerate()
+        .filter(|(_, lenses)| !lenses.is_empty())
+        .map(|(index, lenses)| {
+            let lenses = lenses.iter()
+                .map(|(label, focal_length)| format!("[{} {}]", label, focal_length))
+                .collect::<Vec<_>>()
+                .join(" ");
+            format!("Box {}: {}\n", index, lenses)
+        })
+        .collect()
+}
+
+impl<'a> Iterator for Trace<'a> {
+    type Item = TraceStep<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let instruction = self.instructions.next()?;
+        apply_operation(&mut self.boxes, &parse_operation(instruction));
+
+        let previous_focusing_power = self.focusing_power;
+        self.focusing_power = focusing_power(&self.boxes);
+        self.step += 1;
+
+        Some(TraceStep {
+            step: self.step,
+            instruction,
+            boxes: format_boxes(&self.boxes),
+            focusing_power: self.focusing_power,
+            focusing_power_change: self.focusing_power as i64 - previous_focusing_power as i64,
+        })
+    }
+}
+
+// The `count` steps that changed the focusing power the most, in either direction.
+pub fn largest_focusing_power_changes<'a, 'b>(steps: &'b [TraceStep<'a>], count: usize) -> Vec<&'b TraceStep<'a>> {
+    let mut steps: Vec<_> = steps.iter().collect();
+    steps.sort_by_key(|step| std::cmp::Reverse(step.focusing_power_change.abs()));
+    steps.truncate(count);
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7";
+
+    #[test]
+    fn test_trace() {
+        let steps: Vec<_> = trace(EXAMPLE).collect();
+
+        assert_eq!(steps.len(), 11);
+        assert_eq!(steps[0].to_string(), "After \"rn=1\":\nBox 0: [rn 1]\n");
+        assert_eq!(steps[4].to_string(), "After \"qp-\":\nBox 0: [rn 1] [cm 2]\n");
+        assert_eq!(steps[10].to_string(), "After \"ot=7\":\nBox 0: [rn 1] [cm 2]\nBox 3: [ot 7] [ab 5] [pc 6]\n");
+        assert_eq!(steps[10].focusing_power, 145);
+        assert_eq!(steps.iter().map(|step| step.focusing_power_change).sum::<i64>(), 145);
+    }
+
+    #[test]
+    fn test_stop_at_step() {
+        let last = trace(EXAMPLE).take(3).last().unwrap();
+
+        assert_eq!(last.step, 3);
+        assert_eq!(last.boxes, "Box 0: [rn 1]\nBox 1: [qp 3]\n");
+        assert_eq!(last.focusing_power, 7);
+    }
+
+    #[test]
+    fn test_largest_focusing_power_changes() {
+        let steps: Vec<_> = trace(EXAMPLE).collect();
+        let largest = largest_focusing_power_changes(&steps, 4);
+
+        assert_eq!(largest.iter().map(|step| (step.instruction, step.focusing_power_change)).collect::<Vec<_>>(),
+                   vec![("ot=9", 72), ("pc-", -72), ("pc=6", 72), ("ab=5", 60)]);
+    }
+}
@@ -0,0 +1,295 @@
+// The contraption compiled into a graph of beam segments. Every node is a beam entering a mirror or splitter
+// from some direction, and it owns the tiles its outgoing beams cross until they reach the next mirror or
+// splitter (the next node) or leave the grid. Nodes in the same strongly connected component energise the same
+// tiles, so components are collapsed into a DAG, and the energised tiles of the large components (the loops
+// most beams end up in) are computed once and shared between every entry point that reaches them.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{Beam, Direction, Grid, Tile, DIRECTIONS};
+
+// Components with at least this many nodes get their energised tiles computed once and shared.
+const SHARED_COMPONENT_SIZE: usize = 16;
+
+#[derive(Clone)]
+struct TileSet {
+    words: Vec<u64>,
+}
+
+impl TileSet {
+    fn new(size: usize) -> TileSet {
+        TileSet { words: vec![0; size.div_ceil(64)] }
+    }
+
+    fn insert(&mut self, index: usize) {
+        self.words[index / 64] |= 1 << (index % 64);
+    }
+
+    fn union_with(&mut self, other: &TileSet) {
+        for (word, other) in self.words.iter_mut().zip(&other.words) {
+            *word |= other;
+        }
+    }
+
+    fn len(&self) -> u64 {
+        self.words.iter().map(|word| word.count_ones() as u64).sum()
+    }
+}
+
+pub(crate) struct BeamGraph {
+    width: usize,
+    height: usize,
+    node_ids: HashMap<Beam, usize>,
+    component_of: Vec<usize>,
+    // tiles crossed by the beams leaving the nodes of each component, and the other components they reach
+    component_tiles: Vec<Vec<usize>>,
+    successors: Vec<Vec<usize>>,
+    shared: HashMap<usize, TileSet>,
+}
+
+// Walks from `start` (inclusive) in `direction` over empty tiles. Returns the crossed tiles and the next
+// mirror or splitter reached with the direction it is reached from, if the beam doesn't leave the grid first.
+fn follow_beam(grid: &Grid, start: (usize, usize), direction: Direction) -> (Vec<(usize, usize)>, Option<Beam>) {
+    let grid_size = (grid.width(), grid.height());
+    let mut crossed = vec![];
+    let mut position = Some(start);
+
+    while let Some((x, y)) = position {
+        if grid.get_tile(x, y) != Some(Tile::Empty) {
+            return (crossed, Some(((x, y), direction)));
+        }
+        crossed.push((x, y));
+        position = direction.apply_to_position((x, y), grid_size);
+    }
+
+    (crossed, None)
+}
+
+// Iterative Tarjan, returning the component of every node. Components are numbered in reverse topological
+// order: every edge goes from a component to one with a lower or equal number.
+fn strongly_connected_components(edges: &[Vec<usize>]) -> (Vec<usize>, usize) {
+    const UNVISITED: usize = usize::MAX;
+
+    let mut index = vec![UNVISITED; edges.len()];
+    let mut low_link = vec![0; edges.len()];
+    let mut on_stack = vec![false; edges.len()];
+    let mut component_of = vec![UNVISITED; edges.len()];
+    let mut stack = vec![];
+    let mut components = 0;
+    let mut next_index = 0;
+
+    for root in 0..edges.len() {
+        if index[root] != UNVISITED {
+            continue;
+        }
+
+        let mut call_stack = vec![(root, 0)];
+        index[root] = next_index;
+        low_link[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some((node, edge)) = call_stack.pop() {
+            if let Some(&next) = edges[node].get(edge) {
+                call_stack.push((node, edge + 1));
+
+                if index[next] == UNVISITED {
+                    index[next] = next_index;
+                    low_link[next] = next_index;
+                    next_index += 1;
+                    stack.push(next);
+                    on_stack[next] = true;
+                    call_stack.push((next, 0));
+                } else if on_stack[next] {
+                    low_link[node] = low_link[node].min(index[next]);
+                }
+                continue;
+            }
+
+            if low_link[node] == index[node] {
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    component_of[member] = components;
+                    if member == node {
+                        break;
+                    }
+                }
+                components += 1;
+            }
+
+            if let Some(&(parent, _)) = call_stack.last() {
+                low_link[parent] = low_link[parent].min(low_link[node]);
+            }
+        }
+    }
+
+    (component_of, components)
+}
+
+impl BeamGraph {
+    pub(crate) fn new(grid: &Grid) -> BeamGraph {
+        let (width, height) = (grid.width(), grid.height());
+
+        let mut node_ids = HashMap::new();
+        let mut nodes = vec![];
+        for y in 0..height {
+            for x in 0..width {
+                if grid.get_tile(x, y) != Some(Tile::Empty) {
+                    for direction in DIRECTIONS {
+                        node_ids.insert(((x, y), direction), nodes.len());
+                        nodes.push(((x, y), direction));
+                    }
+                }
+            }
+        }
+
+        let mut edges = vec![vec![]; nodes.len()];
+        let mut crossed_tiles = vec![vec![]; nodes.len()];
+
+        for (id, &((x, y), direction)) in nodes.iter().enumerate() {
+            let tile = grid.get_tile(x, y).expect("nodes are always inside the grid");
+            crossed_tiles[id].push(y * width + x);
+
+            for out_direction in tile.beam_encounter_result(direction) {
+                let Some(next) = out_direction.apply_to_position((x, y), (width, height)) else { continue };
+                let (crossed, reached) = follow_beam(grid, next, out_direction);
+
+                crossed_tiles[id].extend(crossed.into_iter().map(|(x, y)| y * width + x));
+                if let Some(reached) = reached {
+                    edges[id].push(node_ids[&reached]);
+                }
+            }
+        }
+
+        let (component_of, components) = strongly_connected_components(&edges);
+
+        let mut component_sizes = vec![0; components];
+        let mut component_tiles = vec![vec![]; components];
+        let mut successors = vec![HashSet::new(); components];
+        for (node, &component) in component_of.iter().enumerate() {
+            component_sizes[component] += 1;
+            component_tiles[component].append(&mut crossed_tiles[node]);
+            successors[component].extend(edges[node].iter().map(|&next| component_of[next]).filter(|&next| next != component));
+        }
+
+        let mut graph = BeamGraph {
+            width,
+            height,
+            node_ids,
+            component_of,
+            component_tiles,
+            successors: successors.into_iter().map(|successors| successors.into_iter().collect()).collect(),
+            shared: HashMap::new(),
+        };
+
+        // successors always have a lower component number, so shared sets are built on top of the ones before them
+        for (component, &size) in component_sizes.iter().enumerate() {
+            if size >= SHARED_COMPONENT_SIZE {
+                let mut tiles = TileSet::new(width * height);
+                graph.collect_energised(component, &mut tiles);
+                graph.shared.insert(component, tiles);
+            }
+        }
+
+        graph
+    }
+
+    // Adds the tiles energised by a beam reaching `component`, stopping at components that have a shared set.
+    fn collect_energised(&self, component: usize, tiles: &mut TileSet) {
+        let mut visited = HashSet::from([component]);
+        let mut stack = vec![component];
+
+        while let Some(component) = stack.pop() {
+            if let Some(shared) = self.shared.get(&component) {
+                tiles.union_with(shared);
+                continue;
+            }
+
+            for &tile in &self.component_tiles[component] {
+                tiles.insert(tile);
+            }
+
+            for &next in &self.successors[component] {
+                if visited.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn energised_from(&self, grid: &Grid, start_position: (usize, usize), start_direction: Direction) -> u64 {
+        let (crossed, reached) = follow_beam(grid, start_position, start_direction);
+
+        let mut tiles = TileSet::new(self.width * self.height);
+        if let Some(reached) = reached {
+            self.collect_energised(self.component_of[self.node_ids[&reached]], &mut tiles);
+        }
+        for (x, y) in crossed {
+            tiles.insert(y * self.width + x);
+        }
+
+        tiles.len()
+    }
+
+    // Every tile on the edges of the contraption with a beam starting on it in every direction, including the
+    // ones running along the edge or leaving the contraption, since a mirror on the tile can turn them inwards.
+    pub(crate) fn entry_points(&self) -> Vec<Beam> {
+        let (width, height) = (self.width, self.height);
+
+        (0..width).flat_map(|x| (0..height).map(move |y| (x, y)))
+            .filter(|&(x, y)| x == 0 || y == 0 || x == width - 1 || y == height - 1)
+            .flat_map(|position| DIRECTIONS.into_iter().map(move |direction| (position, direction)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_grid, trace_light};
+
+    const EXAMPLE: &str = r".|...\....
+|.-.\.....
+.....|-...
+........|.
+..........
+.........\
+..../.\\..
+.-.-/..|..
+.|....-|.\
+..//.|....";
+
+    #[test]
+    fn test_matches_trace_light() {
+        let grid = parse_grid(EXAMPLE);
+        let graph = BeamGraph::new(&grid);
+
+        assert_eq!(graph.energised_from(&grid, (0, 0), Direction::East), 46);
+        assert_eq!(graph.energised_from(&grid, (3, 0), Direction::South), 51);
+
+        for (position, direction) in graph.entry_points() {
+            assert_eq!(graph.energised_from(&grid, position, direction), trace_light(&grid, position, direction));
+        }
+    }
+
+    #[test]
+    fn test_entry_points_along_the_edges() {
+        // the best beam starts on the bottom edge heading east, the best one heading inwards only energises 6 tiles
+        let grid = parse_grid("\\-.\\\n.\\\\.\n/|/.");
+        let graph = BeamGraph::new(&grid);
+
+        assert_eq!(graph.entry_points().len(), 10 * 4);
+        assert_eq!(crate::best_entry_point(&grid), (((2, 2), Direction::East), 9));
+    }
+
+    #[test]
+    fn test_components() {
+        // 0 -> 1 -> 2 -> 1, 3 -> 0
+        let (component_of, components) = strongly_connected_components(&[vec![1], vec![2], vec![1], vec![0]]);
+
+        assert_eq!(components, 3);
+        assert_eq!(component_of[1], component_of[2]);
+        assert!(component_of[3] > component_of[0] && component_of[0] > component_of[1]);
+    }
+}
@@ -3,6 +3,11 @@ use std::time::Instant;
 
 use rayon::prelude::*;
 
+use crate::beam_graph::BeamGraph;
+
+mod beam_graph;
+mod render;
+
 #[derive(Copy, Clone, PartialEq)]
 enum Tile {
     Empty,
@@ -22,6 +27,9 @@ enum Direction {
     South,
 }
 
+// A beam at a position, moving in a direction.
+type Beam = ((usize, usize), Direction);
+
 const DIRECTIONS: [Direction; 4] = [Direction::North, Direction::East, Direction::West, Direction::South];
 
 impl Direction {
@@ -87,7 +95,7 @@ struct Grid {
 
 impl Grid {
     fn width(&self) -> usize {
-        self.grid.get(0).map(Vec::len).unwrap_or(0)
+        self.grid.first().map(Vec::len).unwrap_or(0)
     }
 
     fn height(&self) -> usize {
@@ -95,34 +103,37 @@ impl Grid {
     }
 
     fn get_tile(&self, x: usize, y: usize) -> Option<Tile> {
-        self.grid.get(y)?.get(x).map(|t| *t)
+        self.grid.get(y)?.get(x).copied()
     }
 }
 
-fn trace_light(grid: &Grid, start_position: (usize, usize), start_direction: Direction) -> u64 {
+// Every position a beam passes through, with the direction it is moving in when it enters it.
+fn trace_beams(grid: &Grid, start_position: (usize, usize), start_direction: Direction) -> HashSet<Beam> {
     let grid_size = (grid.width(), grid.height());
 
     let mut visited_with_direction = HashSet::new();
-    let mut visited = HashSet::new();
-
-    let mut result = 0;
     let mut beams = vec![(start_position, start_direction)];
 
     while let Some(((x, y), previous_direction)) = beams.pop() {
-        visited_with_direction.insert((previous_direction.clone(), (x, y)));
-        if visited.insert((x, y)) {
-            result += 1;
-        }
+        visited_with_direction.insert(((x, y), previous_direction));
 
         let tile = grid.get_tile(x, y).expect("tile has to be in grid");
 
         tile.beam_encounter_result(previous_direction).into_iter()
-            .filter_map(|d| Some((d.apply_to_position((x, y).clone(), grid_size)?, d)))
-            .filter(|(p, d)| !visited_with_direction.contains(&(*d, *p)))
-            .for_each(|(p, d)| beams.push((p, d)));
+            .filter_map(|d| Some((d.apply_to_position((x, y), grid_size)?, d)))
+            .filter(|beam| !visited_with_direction.contains(beam))
+            .for_each(|beam| beams.push(beam));
     }
 
-    result
+    visited_with_direction
+}
+
+fn energised_tiles(beams: &HashSet<Beam>) -> HashSet<(usize, usize)> {
+    beams.iter().map(|&(position, _)| position).collect()
+}
+
+fn trace_light(grid: &Grid, start_position: (usize, usize), start_direction: Direction) -> u64 {
+    energised_tiles(&trace_beams(grid, start_position, start_direction)).len() as u64
 }
 
 fn parse_grid(input: &str) -> Grid {
@@ -147,25 +158,51 @@ fn part1(grid: &Grid) -> u64 {
     trace_light(grid, (0, 0), Direction::East)
 }
 
-fn part2(grid: &Grid) -> u64 {
-    (0..grid.width()).into_par_iter()
-        .flat_map(|x| (0..grid.height()).into_par_iter().map(move |y| (x, y)))
-        .filter(|&(x, y)| x == 0 || y == 0 || x == grid.width() - 1 || y == grid.height() - 1)
-        .flat_map(|position| DIRECTIONS.into_par_iter().map(move |d| (position, d.clone())))
-        .map(|(start_position, start_direction)| trace_light(grid, start_position, start_direction))
-        .max()
+// The entry point that energises the most tiles, with the number of tiles it energises.
+fn best_entry_point(grid: &Grid) -> (Beam, u64) {
+    let graph = BeamGraph::new(grid);
+
+    graph.entry_points().into_par_iter()
+        .map(|(start_position, start_direction)| {
+            ((start_position, start_direction), graph.energised_from(grid, start_position, start_direction))
+        })
+        .max_by_key(|&(_, energised)| energised)
         .expect("map shouldn't be empty")
 }
 
+fn part2(grid: &Grid) -> u64 {
+    best_entry_point(grid).1
+}
+
 fn main() {
     let input = include_str!("input.txt");
     let grid = parse_grid(input);
 
-    let instant = Instant::now();
-    let part1 = part1(&grid);
-    println!("Part 1 in {:?}: {}", instant.elapsed(), part1);
-
-    let instant = Instant::now();
-    let part2 = part2(&grid);
-    println!("Part 2 in {:?}: {}", instant.elapsed(), part2);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    // renders the beams from the top-left corner, or from the best entry point with a trailing `best`
+    let (args, (start_position, start_direction)) = match args.as_slice() {
+        [args @ .., "best"] => (args, best_entry_point(&grid).0),
+        args => (args, ((0, 0), Direction::East)),
+    };
+    let beams = || trace_beams(&grid, start_position, start_direction);
+
+    match args {
+        ["render"] | ["render", "ansi"] => print!("{}", render::render_ansi(&grid, &beams())),
+        ["render", "ppm", path] => {
+            std::fs::write(path, render::render_ppm(&grid, &beams())).expect("could not write ppm file");
+            println!("Written to {path}");
+        }
+        [] => {
+            let instant = Instant::now();
+            let part1 = part1(&grid);
+            println!("Part 1 in {:?}: {}", instant.elapsed(), part1);
+
+            let instant = Instant::now();
+            let part2 = part2(&grid);
+            println!("Part 2 in {:?}: {}", instant.elapsed(), part2);
+        }
+        _ => eprintln!("Usage: day-16 [render [ansi | ppm <path>] [best]]"),
+    }
 }
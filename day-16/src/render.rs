@@ -0,0 +1,194 @@
+// Shows where the light goes, in the terminal or as a PPM image. Beams are drawn on empty tiles the way the
+// puzzle draws them, with arrows or the number of beams crossing the tile, over a highlight on energised tiles.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::{energised_tiles, Beam, Direction, Grid, Tile};
+
+const RESET: &str = "\x1b[0m";
+const ENERGISED_STYLE: &str = "\x1b[30;43m";
+
+const CELL_SIZE: usize = 8;
+const BACKGROUND: [u8; 3] = [24, 24, 32];
+const ENERGISED: [u8; 3] = [255, 196, 0];
+const TILE: [u8; 3] = [220, 220, 220];
+const BEAM: [u8; 3] = [200, 40, 40];
+
+fn tile_char(tile: Tile) -> char {
+    match tile {
+        Tile::Empty => '.',
+        Tile::Mirror => '/',
+        Tile::InvertedMirror => '\\',
+        Tile::VerticalSplitter => '|',
+        Tile::HorizontalSplitter => '-',
+    }
+}
+
+fn arrow(direction: Direction) -> char {
+    match direction {
+        Direction::North => '^',
+        Direction::East => '>',
+        Direction::West => '<',
+        Direction::South => 'v',
+    }
+}
+
+fn directions_by_position(beams: &HashSet<Beam>) -> HashMap<(usize, usize), Vec<Direction>> {
+    let mut directions: HashMap<_, Vec<_>> = HashMap::new();
+    for &(position, direction) in beams {
+        directions.entry(position).or_default().push(direction);
+    }
+    directions
+}
+
+// Mirrors and splitters are always drawn, empty tiles show an arrow for a single beam or how many beams cross them.
+fn cell_char(tile: Tile, directions: Option<&Vec<Direction>>) -> char {
+    match (tile, directions.map(Vec::as_slice)) {
+        (Tile::Empty, Some(&[direction])) => arrow(direction),
+        (Tile::Empty, Some(directions)) if !directions.is_empty() => {
+            char::from_digit(directions.len() as u32, 10).expect("at most four beams cross a tile")
+        }
+        (tile, _) => tile_char(tile),
+    }
+}
+
+pub(crate) fn render_ansi(grid: &Grid, beams: &HashSet<Beam>) -> String {
+    let directions = directions_by_position(beams);
+    let mut output = String::new();
+
+    for y in 0..grid.height() {
+        for x in 0..grid.width() {
+            let tile = grid.get_tile(x, y).expect("position is inside the grid");
+            let c = cell_char(tile, directions.get(&(x, y)));
+
+            if directions.contains_key(&(x, y)) {
+                let _ = write!(output, "{ENERGISED_STYLE}{c}{RESET}");
+            } else {
+                output.push(c);
+            }
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+// Pixels of a cell that are part of the drawing of a tile, relative to the top left corner of the cell.
+fn tile_pixels(tile: Tile) -> Vec<(usize, usize)> {
+    let last = CELL_SIZE - 1;
+    let middle = CELL_SIZE / 2;
+
+    match tile {
+        Tile::Empty => vec![],
+        Tile::Mirror => (0..CELL_SIZE).map(|i| (last - i, i)).collect(),
+        Tile::InvertedMirror => (0..CELL_SIZE).map(|i| (i, i)).collect(),
+        Tile::VerticalSplitter => (0..CELL_SIZE).map(|i| (middle, i)).collect(),
+        Tile::HorizontalSplitter => (0..CELL_SIZE).map(|i| (i, middle)).collect(),
+    }
+}
+
+// Beams are drawn as a line through the middle of the cell along the axis they travel on.
+fn beam_pixels(direction: Direction) -> Vec<(usize, usize)> {
+    let middle = CELL_SIZE / 2;
+
+    match direction {
+        Direction::North | Direction::South => (0..CELL_SIZE).map(|i| (middle, i)).collect(),
+        Direction::East | Direction::West => (0..CELL_SIZE).map(|i| (i, middle)).collect(),
+    }
+}
+
+// Binary PPM (P6) image, which needs no dependencies to write and most image viewers can open.
+pub(crate) fn render_ppm(grid: &Grid, beams: &HashSet<Beam>) -> Vec<u8> {
+    let (width, height) = (grid.width() * CELL_SIZE, grid.height() * CELL_SIZE);
+    let energised = energised_tiles(beams);
+
+    let mut pixels = vec![BACKGROUND; width * height];
+    let mut paint = |x: usize, y: usize, (dx, dy): (usize, usize), color: [u8; 3]| {
+        pixels[(y * CELL_SIZE + dy) * width + x * CELL_SIZE + dx] = color;
+    };
+
+    for y in 0..grid.height() {
+        for x in 0..grid.width() {
+            if energised.contains(&(x, y)) {
+                for dy in 0..CELL_SIZE {
+                    for dx in 0..CELL_SIZE {
+                        paint(x, y, (dx, dy), ENERGISED);
+                    }
+                }
+            }
+        }
+    }
+
+    for &((x, y), direction) in beams {
+        if grid.get_tile(x, y) == Some(Tile::Empty) {
+            for pixel in beam_pixels(direction) {
+                paint(x, y, pixel, BEAM);
+            }
+        }
+    }
+
+    for y in 0..grid.height() {
+        for x in 0..grid.width() {
+            for pixel in tile_pixels(grid.get_tile(x, y).expect("position is inside the grid")) {
+                paint(x, y, pixel, TILE);
+            }
+        }
+    }
+
+    let mut image = format!("P6\n{width} {height}\n255\n").into_bytes();
+    image.extend(pixels.into_iter().flatten());
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_grid, trace_beams};
+
+    const EXAMPLE: &str = r".|...\....
+|.-.\.....
+.....|-...
+........|.
+..........
+.........\
+..../.\\..
+.-.-/..|..
+.|....-|.\
+..//.|....";
+
+    // the highlighted tiles are the only styled text
+    fn without_highlight(rendered: &str) -> String {
+        rendered.replace(ENERGISED_STYLE, "").replace(RESET, "")
+    }
+
+    #[test]
+    fn test_render_ansi() {
+        let grid = parse_grid(EXAMPLE);
+        let rendered = render_ansi(&grid, &trace_beams(&grid, (0, 0), Direction::East));
+
+        assert_eq!(rendered.matches(ENERGISED_STYLE).count(), 46);
+        assert_eq!(without_highlight(&rendered).lines().collect::<Vec<_>>(), vec![
+            r">|<<<\....",
+            r"|v-.\^....",
+            r".v...|->>>",
+            r".v...v^.|.",
+            r".v...v^...",
+            r".v...v^..\",
+            r".v../2\\..",
+            r"<->-/vv|..",
+            r".|<<<2-|.\",
+            r".v//.|.v..",
+        ]);
+    }
+
+    #[test]
+    fn test_render_ppm() {
+        let grid = parse_grid(EXAMPLE);
+        let image = render_ppm(&grid, &trace_beams(&grid, (0, 0), Direction::East));
+
+        let header = format!("P6\n{} {}\n255\n", 10 * CELL_SIZE, 10 * CELL_SIZE);
+        assert!(image.starts_with(header.as_bytes()));
+        assert_eq!(image.len(), header.len() + 10 * CELL_SIZE * 10 * CELL_SIZE * 3);
+    }
+}
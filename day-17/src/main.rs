@@ -1,48 +1,15 @@
-use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashSet};
+use std::collections::HashMap;
 use std::time::Instant;
 
+use grid_pathfinding::{manhattan_distance, Direction, Path, Pathfinder, Turns};
+
 struct Map {
     grid: Vec<Vec<u8>>,
 }
 
-#[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Copy, Hash)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
-impl Direction {
-    fn apply_to_position(&self, (x, y): (usize, usize), (width, height): (usize, usize)) -> Option<(usize, usize)> {
-        let (x, y) = match self {
-            Direction::Up => (x, y.checked_sub(1)?),
-            Direction::Right => (x + 1, y),
-            Direction::Left => (x.checked_sub(1)?, y),
-            Direction::Down => (x, y + 1),
-        };
-
-        if x >= width || y >= height {
-            None
-        } else {
-            Some((x, y))
-        }
-    }
-
-    fn opposite(&self) -> Self {
-        match self {
-            Direction::Up => Direction::Down,
-            Direction::Right => Direction::Left,
-            Direction::Left => Direction::Right,
-            Direction::Down => Direction::Up,
-        }
-    }
-}
-
 impl Map {
     fn width(&self) -> usize {
-        self.grid.get(0).map(Vec::len).unwrap_or(0)
+        self.grid.first().map(Vec::len).unwrap_or(0)
     }
 
     fn height(&self) -> usize {
@@ -50,71 +17,48 @@ impl Map {
     }
 }
 
-#[derive(Eq, PartialEq)]
-struct Path(u64, u64, (usize, usize), Direction);
-
-impl Ord for Path {
-    fn cmp(&self, other: &Self) -> Ordering {
-        other.0.cmp(&self.0)
+fn arrow(direction: Direction) -> char {
+    match direction {
+        Direction::North => '^',
+        Direction::East => '>',
+        Direction::West => '<',
+        Direction::South => 'v',
     }
 }
 
-impl PartialOrd for Path {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+// The crucible can't reverse and has to move between `min_same_direction` and `max_same_direction` blocks
+// in a straight line before turning or stopping at the factory.
+fn find_route(map: &Map, min_same_direction: usize, max_same_direction: usize) -> Option<Path> {
+    if map.width() == 0 || map.height() == 0 {
+        return None;
     }
-}
-
-fn get_min_heat_loss(map: &Map, min_same_direction: u64, max_same_direction: u64) -> u64 {
-    let mut min_heap = BinaryHeap::new();
-    let mut seen = HashSet::new();
-
-    min_heap.push(Path(map.grid[0][1] as u64, 1, (1, 0), Direction::Right));
-    min_heap.push(Path(map.grid[1][0] as u64, 1, (0, 1), Direction::Down));
 
-    while let Some(Path(accumulated_heat_loss,
-                        consecutive_moves_in_same_direction,
-                        (x, y),
-                        last_direction)) = min_heap.pop() {
-        if x == map.width() - 1 && y == map.height() - 1 && consecutive_moves_in_same_direction >= min_same_direction {
-            return accumulated_heat_loss;
-        }
-
-        for direction in &[Direction::Up, Direction::Right, Direction::Left, Direction::Down] {
-            if *direction == last_direction.opposite() {
-                continue;
-            }
+    let min_heat_loss = map.grid.iter().flatten().min().copied().unwrap_or(0) as u64;
 
-            if *direction != last_direction && consecutive_moves_in_same_direction < min_same_direction {
-                continue;
-            }
-
-            let Some((x, y)) = direction.apply_to_position((x, y), (map.width(), map.height())) else {
-                continue;
-            };
+    Pathfinder::new(map.width(), map.height(), |(x, y), _| Some(map.grid[y][x] as u64))
+        .turns(Turns::NO_REVERSING)
+        .runs(min_same_direction, Some(max_same_direction))
+        .heuristic(manhattan_distance(min_heat_loss))
+        .find_path((0, 0), (map.width() - 1, map.height() - 1))
+}
 
-            let accumulated_heat_loss = accumulated_heat_loss + map.grid[y][x] as u64;
+fn get_min_heat_loss(map: &Map, min_same_direction: usize, max_same_direction: usize) -> Option<u64> {
+    find_route(map, min_same_direction, max_same_direction).map(|route| route.cost())
+}
 
-            let consecutive_moves_in_same_direction = if *direction == last_direction {
-                consecutive_moves_in_same_direction + 1
-            } else {
-                1
-            };
+// The city map with every block the route moves into replaced by the direction it moves in.
+fn render_route(map: &Map, route: &Path) -> String {
+    let arrows: HashMap<_, _> = route.steps.iter().map(|step| (step.position, arrow(step.direction))).collect();
 
-            if consecutive_moves_in_same_direction > max_same_direction {
-                continue;
-            }
-
-            if seen.insert(((x, y), *direction, consecutive_moves_in_same_direction)) {
-                min_heap.push(Path(accumulated_heat_loss,
-                                   consecutive_moves_in_same_direction,
-                                   (x, y),
-                                   *direction));
-            }
+    let mut output = String::new();
+    for (y, row) in map.grid.iter().enumerate() {
+        for (x, heat_loss) in row.iter().enumerate() {
+            let digit = char::from_digit(*heat_loss as u32, 10).expect("heat loss is a single digit");
+            output.push(arrows.get(&(x, y)).copied().unwrap_or(digit));
         }
+        output.push('\n');
     }
-
-    u64::MAX
+    output
 }
 
 fn parse_map(input: &str) -> Map {
@@ -134,11 +78,96 @@ fn main() {
     let input = include_str!("input.txt");
     let map = parse_map(input);
 
-    let instant = Instant::now();
-    let part1 = get_min_heat_loss(&map, 1, 3);
-    println!("Part 1 in {:?}: {}", instant.elapsed(), part1);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["route"] | ["route", "ultra"] => {
+            let (min_same_direction, max_same_direction) = if args.len() == 2 { (4, 10) } else { (1, 3) };
+            match find_route(&map, min_same_direction, max_same_direction) {
+                Some(route) => {
+                    print!("{}", render_route(&map, &route));
+                    println!("Heat loss: {}", route.cost());
+                }
+                None => println!("No route to the factory"),
+            }
+        }
+        [] => {
+            let instant = Instant::now();
+            let part1 = get_min_heat_loss(&map, 1, 3);
+            println!("Part 1 in {:?}: {:?}", instant.elapsed(), part1);
+
+            let instant = Instant::now();
+            let part2 = get_min_heat_loss(&map, 4, 10);
+            println!("Part 2 in {:?}: {:?}", instant.elapsed(), part2);
+        }
+        _ => eprintln!("Usage: day-17 [route [ultra]]"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "2413432311323
+3215453535623
+3255245654254
+3446585845452
+4546657867536
+1438598798454
+4457876987766
+3637877979653
+4654967986887
+4564679986453
+1224686865563
+2546548887735
+4322674655533";
+
+    // Checks the route is connected, respects the run lengths and accumulates the heat loss correctly.
+    fn assert_valid(map: &Map, route: &Path, min_same_direction: usize, max_same_direction: usize) {
+        let mut position = (0, 0);
+        let mut heat_loss = 0;
+        let mut runs = vec![];
+
+        for step in &route.steps {
+            position = step.direction.apply_to_position(position, (map.width(), map.height())).unwrap();
+            heat_loss += map.grid[position.1][position.0] as u64;
+            assert_eq!((step.position, step.cost), (position, heat_loss));
+
+            match runs.last_mut() {
+                Some((direction, length)) if *direction == step.direction => *length += 1,
+                _ => runs.push((step.direction, 1)),
+            }
+        }
 
-    let instant = Instant::now();
-    let part2 = get_min_heat_loss(&map, 4, 10);
-    println!("Part 2 in {:?}: {}", instant.elapsed(), part2);
+        assert_eq!(position, (map.width() - 1, map.height() - 1));
+        assert!(runs.iter().all(|&(_, length)| (min_same_direction..=max_same_direction).contains(&length)));
+    }
+
+    #[test]
+    fn test_example_routes() {
+        let map = parse_map(EXAMPLE);
+
+        let route = find_route(&map, 1, 3).unwrap();
+        assert_eq!(route.cost(), 102);
+        assert_valid(&map, &route, 1, 3);
+
+        let route = find_route(&map, 4, 10).unwrap();
+        assert_eq!(route.cost(), 94);
+        assert_valid(&map, &route, 4, 10);
+    }
+
+    #[test]
+    fn test_render_route() {
+        let map = parse_map("1911\n1191\n9111");
+        let route = find_route(&map, 1, 3).unwrap();
+
+        assert_eq!(route.cost(), 5);
+        assert_eq!(render_route(&map, &route), "1911\nv>91\n9v>>\n");
+    }
+
+    #[test]
+    fn test_no_route() {
+        assert!(find_route(&parse_map("111"), 4, 10).is_none());
+        assert_eq!(get_min_heat_loss(&parse_map("12345"), 1, 4), Some(14));
+        assert_eq!(get_min_heat_loss(&parse_map("7"), 1, 3), Some(0));
+    }
 }
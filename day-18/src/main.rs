@@ -1,25 +1,57 @@
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
+use polygon::Polygon;
+
+mod render;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Color {
+    red: u8,
+    green: u8,
+    blue: u8,
+}
+
+impl FromStr for Color {
+    type Err = ();
+
+    // `#rrggbb`, with or without the parentheses around it in the dig plan.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.trim_start_matches('(').trim_end_matches(')').strip_prefix('#').ok_or(())?;
+        if hex.len() != 6 {
+            return Err(());
+        }
+
+        let channel = |index: usize| u8::from_str_radix(hex.get(index..index + 2).ok_or(())?, 16).map_err(|_| ());
+        Ok(Color { red: channel(0)?, green: channel(2)?, blue: channel(4)? })
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+    }
+}
+
+struct Trench {
+    direction: Direction,
+    meters: isize,
+    color: Color,
+}
+
 struct Map {
-    paths: Vec<(Direction, isize)>,
+    paths: Vec<Trench>,
 }
 
 impl Map {
-    fn get_interior_area(&self) -> isize {
-        let mut area = 0;
-        let mut lat = 0;
-        for (direction, meters) in &self.paths {
-            match direction {
-                Direction::Up => lat -= meters,
-                Direction::Down => {
-                    area += meters;
-                    lat += meters;
-                }
-                Direction::Left => area += meters * (lat + 1),
-                Direction::Right => area -= meters * lat,
-            };
-        }
-        area + 1
+    // The trench loop through the centres of the dug cubes, starting at the origin.
+    fn polygon(&self) -> Polygon {
+        Polygon::from_steps((0, 0), self.paths.iter().map(|trench| (trench.direction.offset(), trench.meters as i64)))
+    }
+
+    // Cubes inside the trench, the trench itself included.
+    fn get_interior_area(&self) -> i64 {
+        self.polygon().enclosed_points()
     }
 }
 
@@ -30,6 +62,17 @@ enum Direction {
     Right,
 }
 
+impl Direction {
+    fn offset(&self) -> (i64, i64) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        }
+    }
+}
+
 impl FromStr for Direction {
     type Err = ();
 
@@ -46,21 +89,24 @@ impl FromStr for Direction {
 
 fn parse_map(input: &str) -> Map {
     let paths = input.lines().map(|line| {
-        let [direction, meters, _] = line.split_whitespace().collect::<Vec<&str>>()[..] else {
+        let [direction, meters, color] = line.split_whitespace().collect::<Vec<&str>>()[..] else {
             panic!("Invalid input");
         };
         let direction = direction.parse::<Direction>().unwrap();
         let meters = meters.parse::<isize>().unwrap();
+        let color = color.parse::<Color>().expect("Invalid color");
 
-        (direction, meters)
+        Trench { direction, meters, color }
     }).collect();
 
     Map { paths }
 }
 
+// The real instructions are hidden in the colors, but each trench still keeps the color of its line.
 fn parse_map_from_colors(input: &str) -> Map {
     let paths = input.lines().map(|line| {
-        let hex = line.split_whitespace().collect::<Vec<&str>>()[2].trim_start_matches("(#").trim_end_matches(")");
+        let color = line.split_whitespace().collect::<Vec<&str>>()[2];
+        let hex = color.trim_start_matches("(#").trim_end_matches(')');
         let meters = isize::from_str_radix(&hex[0..5], 16).unwrap();
 
         let direction = isize::from_str_radix(&hex[5..6], 16).unwrap();
@@ -71,7 +117,9 @@ fn parse_map_from_colors(input: &str) -> Map {
             3 => Direction::Up,
             _ => panic!("Invalid input"),
         };
-        (direction, meters)
+        let color = color.parse::<Color>().expect("Invalid color");
+
+        Trench { direction, meters, color }
     }).collect();
 
     Map { paths }
@@ -79,9 +127,63 @@ fn parse_map_from_colors(input: &str) -> Map {
 
 fn main() {
     let input = include_str!("input.txt");
-    let map = parse_map(input);
-    println!("Part 1: {}", map.get_interior_area());
 
-    let map = parse_map_from_colors(input);
-    println!("Part 2: {}", map.get_interior_area());
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["svg", path] | ["svg", path, "decoded"] => {
+            let map = if args.len() == 3 { parse_map_from_colors(input) } else { parse_map(input) };
+            std::fs::write(path, render::render_svg(&map)).expect("could not write svg file");
+            println!("Written to {path}");
+        }
+        [] => {
+            let map = parse_map(input);
+            println!("Part 1: {}", map.get_interior_area());
+
+            let map = parse_map_from_colors(input);
+            println!("Part 2: {}", map.get_interior_area());
+        }
+        _ => eprintln!("Usage: day-18 [svg <path> [decoded]]"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "R 6 (#70c710)
+D 5 (#0dc571)
+L 2 (#5713f0)
+D 2 (#d2c081)
+R 2 (#59c680)
+D 2 (#411b91)
+L 5 (#8ceee2)
+U 2 (#caa173)
+L 1 (#1b58a2)
+U 2 (#caa171)
+R 2 (#7807d2)
+U 3 (#a77fa3)
+L 2 (#015232)
+U 2 (#7a21e3)";
+
+    #[test]
+    fn test_example() {
+        let map = parse_map(EXAMPLE);
+        assert_eq!(map.get_interior_area(), 62);
+        assert_eq!(map.polygon().boundary_points(), 38);
+        assert!(map.polygon().is_simple());
+
+        assert_eq!(parse_map_from_colors(EXAMPLE).get_interior_area(), 952408144115);
+    }
+
+    #[test]
+    fn test_colors() {
+        let map = parse_map(EXAMPLE);
+        assert_eq!(map.paths[0].color, Color { red: 0x70, green: 0xc7, blue: 0x10 });
+        assert_eq!(map.paths[13].color.to_string(), "#7a21e3");
+        assert_eq!(parse_map_from_colors(EXAMPLE).paths[1].color, map.paths[1].color);
+
+        assert_eq!("#0dc571".parse::<Color>(), Ok(Color { red: 0x0d, green: 0xc5, blue: 0x71 }));
+        assert_eq!("(#0dc5)".parse::<Color>(), Err(()));
+        assert_eq!("0dc571".parse::<Color>(), Err(()));
+    }
 }
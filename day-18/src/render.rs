@@ -0,0 +1,98 @@
+// SVG export of the lagoon: the interior filled in and every trench drawn with its own color.
+// Plans are scaled to fit the image, since decoded plans are millions of meters wide.
+
+use std::fmt::Write;
+
+use crate::Map;
+
+const IMAGE_SIZE: f64 = 800.0;
+const MARGIN: f64 = 10.0;
+const INTERIOR_FILL: &str = "#3b5f7a";
+
+pub(crate) fn render_svg(map: &Map) -> String {
+    let polygon = map.polygon();
+    let vertices = polygon.vertices();
+
+    let (min_x, max_x) = vertices.iter().fold((i64::MAX, i64::MIN), |(min, max), &(x, _)| (min.min(x), max.max(x)));
+    let (min_y, max_y) = vertices.iter().fold((i64::MAX, i64::MIN), |(min, max), &(_, y)| (min.min(y), max.max(y)));
+
+    // a trench is one meter wide, so the lagoon spans one more meter than its vertices
+    let (plan_width, plan_height) = ((max_x - min_x + 1) as f64, (max_y - min_y + 1) as f64);
+    let scale = (IMAGE_SIZE - 2.0 * MARGIN) / plan_width.max(plan_height);
+    let (width, height) = (plan_width * scale + 2.0 * MARGIN, plan_height * scale + 2.0 * MARGIN);
+
+    // the centre of the cube at (x, y)
+    let to_image = |(x, y): (i64, i64)| {
+        ((x - min_x) as f64 * scale + MARGIN + scale / 2.0, (y - min_y) as f64 * scale + MARGIN + scale / 2.0)
+    };
+    let trench_width = scale.max(1.0);
+
+    let mut svg = String::new();
+    let _ = writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width:.0}" height="{height:.0}" viewBox="0 0 {width:.2} {height:.2}">"#);
+    let _ = writeln!(svg, r#"<rect width="{width:.2}" height="{height:.2}" fill="white"/>"#);
+
+    let points: Vec<String> = vertices.iter().map(|&vertex| {
+        let (x, y) = to_image(vertex);
+        format!("{x:.2},{y:.2}")
+    }).collect();
+    let _ = writeln!(svg, r#"<polygon points="{}" fill="{INTERIOR_FILL}"/>"#, points.join(" "));
+
+    for ((start, end), trench) in polygon.edges().zip(&map.paths) {
+        let ((x1, y1), (x2, y2)) = (to_image(start), to_image(end));
+        let _ = writeln!(svg, r#"<line x1="{x1:.2}" y1="{y1:.2}" x2="{x2:.2}" y2="{y2:.2}" stroke="{}" stroke-width="{trench_width:.2}" stroke-linecap="square"/>"#, trench.color);
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_map, parse_map_from_colors};
+
+    const EXAMPLE: &str = "R 6 (#70c710)
+D 5 (#0dc571)
+L 2 (#5713f0)
+D 2 (#d2c081)
+R 2 (#59c680)
+D 2 (#411b91)
+L 5 (#8ceee2)
+U 2 (#caa173)
+L 1 (#1b58a2)
+U 2 (#caa171)
+R 2 (#7807d2)
+U 3 (#a77fa3)
+L 2 (#015232)
+U 2 (#7a21e3)";
+
+    // every number in the attributes of the lines and the polygon
+    fn coordinates(svg: &str) -> Vec<f64> {
+        svg.lines()
+            .filter(|line| line.starts_with("<line") || line.starts_with("<polygon"))
+            .flat_map(|line| line.split(['"', ' ', ',']))
+            .filter_map(|value| value.parse().ok())
+            .collect()
+    }
+
+    #[test]
+    fn test_trench_colors() {
+        let svg = render_svg(&parse_map(EXAMPLE));
+
+        assert_eq!(svg.matches("<line").count(), 14);
+        assert_eq!(svg.matches("<polygon").count(), 1);
+        assert!(svg.contains(r##"stroke="#70c710""##));
+        assert!(svg.contains(r##"stroke="#7a21e3""##));
+    }
+
+    #[test]
+    fn test_scaled_to_fit() {
+        for map in [parse_map(EXAMPLE), parse_map_from_colors(EXAMPLE)] {
+            let svg = render_svg(&map);
+
+            let header = svg.lines().next().unwrap();
+            assert!(header.contains(r#"width="800""#) || header.contains(r#"height="800""#));
+            assert!(coordinates(&svg).iter().all(|&value| (0.0..=IMAGE_SIZE).contains(&value)));
+        }
+    }
+}
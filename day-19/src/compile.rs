@@ -0,0 +1,288 @@
+// Flattens a workflow system into a single decision tree, with every redirect inlined, every branch no part can
+// take pruned and every condition whose branches lead to the same decision removed. The tree accepts exactly the
+// same parts, and can be turned back into workflows, into the disjoint ranges it accepts, or exported as DOT or JSON.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::{Condition, RangedPart, Result, Rule, RuleResult, Schema, System, WorkflowError};
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum Decision<'a> {
+    Result(Result),
+    Condition { condition: Condition<'a>, then: Box<Decision<'a>>, otherwise: Box<Decision<'a>> },
+}
+
+// Workflows reached through different rules are inlined once per rule, so a system that is not a tree
+// can compile into a much bigger decision tree.
+pub(crate) fn compile<'a>(system: &System<'a>) -> std::result::Result<Decision<'a>, WorkflowError<'a>> {
+    let mut visiting = vec![];
+    compile_redirect(system, "in", system.schema.all_ratings(), &mut visiting)
+}
+
+fn compile_redirect<'a>(
+    system: &System<'a>,
+    name: &'a str,
+    ranged_part: RangedPart<'a>,
+    visiting: &mut Vec<&'a str>,
+) -> std::result::Result<Decision<'a>, WorkflowError<'a>> {
+    let workflow = system.workflows.get(name).ok_or(WorkflowError::MissingWorkflow(name))?;
+    if visiting.contains(&name) {
+        return Err(WorkflowError::Cycle(name));
+    }
+
+    visiting.push(name);
+    let decision = compile_rules(system, &workflow.rules, ranged_part, visiting)?;
+    visiting.pop();
+
+    Ok(decision)
+}
+
+fn compile_rules<'a>(
+    system: &System<'a>,
+    rules: &[Rule<'a>],
+    ranged_part: RangedPart<'a>,
+    visiting: &mut Vec<&'a str>,
+) -> std::result::Result<Decision<'a>, WorkflowError<'a>> {
+    let Some((rule, rest_of_rules)) = rules.split_first() else {
+        // like `System::explain`, parts that no rule matches are accepted
+        return Ok(Decision::Result(Result::Accept));
+    };
+
+    let resolve = |ranged_part, visiting: &mut Vec<&'a str>| match rule.result() {
+        RuleResult::Result(result) => Ok(Decision::Result(*result)),
+        RuleResult::Redirect(name) => compile_redirect(system, name, ranged_part, visiting),
+    };
+
+    let Some(condition) = rule.condition() else {
+        return resolve(ranged_part, visiting);
+    };
+
+    match rule.split(ranged_part) {
+        (Some(matched), Some(rest)) => {
+            let then = resolve(matched, visiting)?;
+            let otherwise = compile_rules(system, rest_of_rules, rest, visiting)?;
+
+            if then == otherwise {
+                Ok(then)
+            } else {
+                Ok(Decision::Condition { condition, then: Box::new(then), otherwise: Box::new(otherwise) })
+            }
+        }
+        (Some(matched), None) => resolve(matched, visiting),
+        (None, Some(rest)) => compile_rules(system, rest_of_rules, rest, visiting),
+        (None, None) => unreachable!("a split always keeps the values it was given"),
+    }
+}
+
+impl<'a> Decision<'a> {
+    // The ranges of parts the tree accepts, none of them overlapping.
+    pub(crate) fn accepted_ranges(&self, schema: &Schema<'a>) -> Vec<RangedPart<'a>> {
+        let mut accepted = vec![];
+        self.collect_accepted(schema.all_ratings(), &mut accepted);
+        accepted
+    }
+
+    fn collect_accepted(&self, ranged_part: RangedPart<'a>, accepted: &mut Vec<RangedPart<'a>>) {
+        match self {
+            Decision::Result(Result::Accept) => accepted.push(ranged_part),
+            Decision::Result(Result::Reject) => {}
+            Decision::Condition { condition, then, otherwise } => {
+                let (matched, rest) = condition.split(ranged_part);
+                if let Some(matched) = matched {
+                    then.collect_accepted(matched, accepted);
+                }
+                if let Some(rest) = rest {
+                    otherwise.collect_accepted(rest, accepted);
+                }
+            }
+        }
+    }
+
+    // The tree back in the workflow syntax, starting at `in`. Every chain of `otherwise` branches becomes the
+    // rules of one workflow, and identical subtrees share the same workflow. Schemas other than the default one
+    // are written first, so the workflows parse back the same way.
+    pub(crate) fn to_workflows(&self, schema: &Schema) -> String {
+        let mut workflows = vec![];
+        if *schema != Schema::default() {
+            workflows.push(schema.to_string());
+        }
+
+        if let Decision::Result(result) = self {
+            workflows.push(format!("in{{{result}}}"));
+        } else {
+            let mut names = HashMap::new();
+            let mut compiled = vec![];
+            self.workflow_name(&mut names, &mut compiled);
+            workflows.extend(compiled);
+        }
+
+        workflows.into_iter().map(|workflow| workflow + "\n").collect()
+    }
+
+    fn workflow_name<'b>(&'b self, names: &mut HashMap<&'b Decision<'a>, String>, workflows: &mut Vec<String>) -> String {
+        if let Decision::Result(result) = self {
+            return result.to_string();
+        }
+        if let Some(name) = names.get(self) {
+            return name.clone();
+        }
+
+        let name = if workflows.is_empty() { "in".to_string() } else { format!("w{}", workflows.len()) };
+        let index = workflows.len();
+        names.insert(self, name.clone());
+        workflows.push(String::new());
+
+        let mut rules = vec![];
+        let mut current = self;
+        while let Decision::Condition { condition, then, otherwise } = current {
+            rules.push(format!("{condition}:{}", then.workflow_name(names, workflows)));
+            current = otherwise;
+        }
+        rules.push(current.workflow_name(names, workflows));
+
+        workflows[index] = format!("{name}{{{}}}", rules.join(","));
+        name
+    }
+
+    pub(crate) fn to_dot(&self) -> String {
+        let mut ids = HashMap::new();
+        let mut dot = String::from("digraph workflows {\n");
+        self.dot_node(&mut ids, &mut dot);
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn dot_node<'b>(&'b self, ids: &mut HashMap<&'b Decision<'a>, usize>, dot: &mut String) -> usize {
+        if let Some(&id) = ids.get(self) {
+            return id;
+        }
+
+        let id = ids.len();
+        ids.insert(self, id);
+
+        match self {
+            Decision::Result(result) => {
+                let _ = writeln!(dot, "    n{id} [label=\"{result}\", shape=box];");
+            }
+            Decision::Condition { condition, then, otherwise } => {
+                let _ = writeln!(dot, "    n{id} [label=\"{condition}\"];");
+                let then = then.dot_node(ids, dot);
+                let otherwise = otherwise.dot_node(ids, dot);
+                let _ = writeln!(dot, "    n{id} -> n{then} [label=\"true\"];");
+                let _ = writeln!(dot, "    n{id} -> n{otherwise} [label=\"false\"];");
+            }
+        }
+
+        id
+    }
+
+    pub(crate) fn to_json(&self) -> String {
+        match self {
+            Decision::Result(result) => format!("\"{result}\""),
+            Decision::Condition { condition, then, otherwise } => format!(
+                r#"{{"condition":"{condition}","then":{},"else":{}}}"#,
+                then.to_json(),
+                otherwise.to_json(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_system;
+
+    const EXAMPLE: &str = "px{a<2006:qkq,m>2090:A,rfg}
+pv{a>1716:R,A}
+lnx{m>1548:A,A}
+rfg{s<537:gd,x>2440:R,A}
+qs{s>3448:A,lnx}
+qkq{x<1416:A,crn}
+crn{x>2662:A,R}
+in{s<1351:px,qqz}
+qqz{s>2770:qs,m<1801:hdj,R}
+gd{a>3333:R,R}
+hdj{m>838:A,pv}
+
+{x=787,m=2655,a=1222,s=2876}
+{x=1679,m=44,a=2067,s=496}
+{x=2036,m=264,a=79,s=2244}
+{x=2461,m=1339,a=466,s=291}
+{x=2127,m=1623,a=2188,s=1013}";
+
+    fn overlap(a: &RangedPart, b: &RangedPart) -> bool {
+        a.ranges.iter().zip(&b.ranges).all(|((_, a), (_, b))| !a.intersection(b).is_empty())
+    }
+
+    #[test]
+    fn test_accepted_ranges() {
+        let system = parse_system(EXAMPLE);
+        let ranges = compile(&system).unwrap().accepted_ranges(&system.schema);
+
+        assert_eq!(ranges.iter().map(RangedPart::multiply_all).sum::<u128>(), 167409079868000);
+        for (i, a) in ranges.iter().enumerate() {
+            for b in &ranges[i + 1..] {
+                assert!(!overlap(a, b));
+            }
+        }
+    }
+
+    #[test]
+    fn test_workflows_are_equivalent() {
+        let system = parse_system(EXAMPLE);
+        let workflows = compile(&system).unwrap().to_workflows(&system.schema);
+        let compiled = parse_system(&workflows);
+
+        assert!(workflows.starts_with("in{s<1351:"));
+        // qs and lnx always accept, so qqz's first rule sends parts straight to A
+        assert!(workflows.contains("s>2770:A"));
+        assert!(workflows.lines().count() < system.workflows.len());
+
+        assert_eq!(crate::part_2(&compiled), crate::part_2(&system));
+        for part in &system.parts {
+            assert!(compiled.execute_workflow(part) == system.execute_workflow(part));
+        }
+    }
+
+    #[test]
+    fn test_collapses_constant_systems() {
+        let system = parse_system("in{x>10:a,b}\na{m<5:R,R}\nb{a>4000:A,R}\n");
+        let decision = compile(&system).unwrap();
+
+        assert_eq!(decision, Decision::Result(Result::Reject));
+        assert_eq!(decision.to_workflows(&system.schema), "in{R}\n");
+        assert!(decision.accepted_ranges(&system.schema).is_empty());
+    }
+
+    #[test]
+    fn test_exports() {
+        let decision = compile(&parse_system("in{x>10:A,R}\n")).unwrap();
+
+        assert_eq!(decision.to_json(), r#"{"condition":"x>10","then":"A","else":"R"}"#);
+        assert_eq!(decision.to_dot(), "digraph workflows {
+    n0 [label=\"x>10\"];
+    n1 [label=\"A\", shape=box];
+    n2 [label=\"R\", shape=box];
+    n0 -> n1 [label=\"true\"];
+    n0 -> n2 [label=\"false\"];
+}
+");
+    }
+
+    #[test]
+    fn test_custom_schema() {
+        let system = parse_system("schema{weight=-50..=50,priority=1..=3}\nin{weight<0:R,priority!=3:low,A}\nlow{weight<=10:R,A}\n");
+        let workflows = compile(&system).unwrap().to_workflows(&system.schema);
+
+        assert!(workflows.starts_with("schema{weight=-50..=50,priority=1..=3}\nin{"));
+        assert_eq!(crate::part_2(&parse_system(&workflows)), crate::part_2(&system));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(compile(&parse_system("in{x>10:a,R}\n")), Err(WorkflowError::MissingWorkflow("a")));
+        assert_eq!(compile(&parse_system("in{x>10:a,R}\na{in}\n")), Err(WorkflowError::Cycle("in")));
+    }
+}
@@ -0,0 +1,134 @@
+// The path a single part takes through the workflows, e.g. `in → qqz: s>2770 → qs → lnx: m>1548 → A`.
+
+use std::fmt::{Display, Formatter};
+
+use crate::{Condition, Part, Result, RuleResult, System, WorkflowError};
+
+pub(crate) struct TraceStep<'a> {
+    workflow: &'a str,
+    // index of the rule that matched in the workflow, None if no rule matched
+    rule: Option<usize>,
+    // the comparison that fired, None for the last rule of a workflow that always fires
+    condition: Option<Condition<'a>>,
+}
+
+pub(crate) struct Trace<'a> {
+    steps: Vec<TraceStep<'a>>,
+    result: std::result::Result<Result, WorkflowError<'a>>,
+}
+
+impl<'a> System<'a> {
+    pub(crate) fn explain(&self, part: &Part) -> Trace<'a> {
+        let mut steps: Vec<TraceStep<'a>> = vec![];
+        let mut name = "in";
+
+        let result = loop {
+            if steps.iter().any(|step| step.workflow == name) {
+                break Err(WorkflowError::Cycle(name));
+            }
+            let Some((&workflow_name, workflow)) = self.workflows.get_key_value(name) else {
+                break Err(WorkflowError::MissingWorkflow(name));
+            };
+
+            let matched = workflow.matching_rule(part);
+            steps.push(TraceStep {
+                workflow: workflow_name,
+                rule: matched.map(|(index, _)| index),
+                condition: matched.and_then(|(_, rule)| rule.condition()),
+            });
+
+            match matched.map(|(_, rule)| rule.result()) {
+                Some(RuleResult::Redirect(next)) => name = next,
+                Some(RuleResult::Result(result)) => break Ok(*result),
+                // parts that no rule matches are accepted
+                None => break Ok(Result::Accept),
+            }
+        };
+
+        Trace { steps, result }
+    }
+}
+
+impl<'a> Trace<'a> {
+    pub(crate) fn result(&self) -> &std::result::Result<Result, WorkflowError<'a>> {
+        &self.result
+    }
+}
+
+impl Display for TraceStep<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (self.condition, self.rule) {
+            (Some(condition), _) => write!(f, "{}: {condition}", self.workflow),
+            (None, Some(_)) => write!(f, "{}", self.workflow),
+            (None, None) => write!(f, "{}: no rule matched", self.workflow),
+        }
+    }
+}
+
+impl Display for Trace<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for step in &self.steps {
+            write!(f, "{step} → ")?;
+        }
+        match &self.result {
+            Ok(result) => write!(f, "{result}"),
+            Err(error) => write!(f, "error: {error}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_part, parse_system, Result};
+
+    const WORKFLOWS: &str = "px{a<2006:qkq,m>2090:A,rfg}
+pv{a>1716:R,A}
+lnx{m>1548:A,A}
+rfg{s<537:gd,x>2440:R,A}
+qs{s>3448:A,lnx}
+qkq{x<1416:A,crn}
+crn{x>2662:A,R}
+in{s<1351:px,qqz}
+qqz{s>2770:qs,m<1801:hdj,R}
+gd{a>3333:R,R}
+hdj{m>838:A,pv}
+";
+
+    fn explain(workflows: &str, part: &str) -> String {
+        let system = parse_system(workflows);
+        system.explain(&parse_part(part, &system.schema)).to_string()
+    }
+
+    #[test]
+    fn test_example_parts() {
+        assert_eq!(explain(WORKFLOWS, "{x=787,m=2655,a=1222,s=2876}"), "in → qqz: s>2770 → qs → lnx: m>1548 → A");
+        assert_eq!(explain(WORKFLOWS, "{x=1679,m=44,a=2067,s=496}"), "in: s<1351 → px → rfg: s<537 → gd → R");
+        assert_eq!(explain(WORKFLOWS, "{x=2036,m=264,a=79,s=2244}"), "in → qqz: m<1801 → hdj → pv → A");
+        assert_eq!(explain(WORKFLOWS, "{x=2461,m=1339,a=466,s=291}"), "in: s<1351 → px: a<2006 → qkq → crn → R");
+        assert_eq!(explain(WORKFLOWS, "{x=2127,m=1623,a=2188,s=1013}"), "in: s<1351 → px → rfg → A");
+        assert_eq!(explain(WORKFLOWS, "{x=1000,m=1,a=1,s=1}"), "in: s<1351 → px: a<2006 → qkq: x<1416 → A");
+    }
+
+    #[test]
+    fn test_example_results() {
+        let system = parse_system(WORKFLOWS);
+        let results = [
+            ("{x=787,m=2655,a=1222,s=2876}", Result::Accept),
+            ("{x=1679,m=44,a=2067,s=496}", Result::Reject),
+            ("{x=2036,m=264,a=79,s=2244}", Result::Accept),
+            ("{x=2461,m=1339,a=466,s=291}", Result::Reject),
+            ("{x=2127,m=1623,a=2188,s=1013}", Result::Accept),
+        ];
+
+        for (part, result) in results {
+            assert_eq!(system.explain(&parse_part(part, &system.schema)).result(), &Ok(result), "{part}");
+        }
+    }
+
+    #[test]
+    fn test_broken_systems() {
+        assert_eq!(explain("in{x>10:a,R}\na{in}\n", "{x=11,m=0,a=0,s=0}"), "in: x>10 → a → error: workflow `in` is part of a cycle");
+        assert_eq!(explain("in{x>10:a,R}\n", "{x=11,m=0,a=0,s=0}"), "in: x>10 → error: workflow `a` doesn't exist");
+        assert_eq!(explain("in{x>10:R}\n", "{x=1,m=0,a=0,s=0}"), "in: no rule matched → A");
+    }
+}
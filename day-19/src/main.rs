@@ -1,198 +1,392 @@
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
-struct Part {
-    x: u64,
-    m: u64,
-    a: u64,
-    s: u64,
+mod compile;
+mod explain;
+mod validate;
+
+// The categories parts are rated in, with the ratings a part can have in each of them.
+#[derive(PartialEq, Eq, Debug)]
+struct Schema<'a> {
+    categories: Vec<(&'a str, Range)>,
+}
+
+impl Default for Schema<'_> {
+    // the categories of the puzzle, x, m, a and s, all rated from 1 to 4000
+    fn default() -> Self {
+        Schema { categories: ["x", "m", "a", "s"].into_iter().map(|name| (name, Range::new(1, 4000))).collect() }
+    }
+}
+
+impl Display for Schema<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let categories: Vec<String> = self.categories.iter().map(|(name, range)| format!("{name}={range}")).collect();
+        write!(f, "schema{{{}}}", categories.join(","))
+    }
+}
+
+impl<'a> Schema<'a> {
+    fn category(&self, name: &str) -> Option<&'a str> {
+        self.categories.iter().map(|&(category, _)| category).find(|&category| category == name)
+    }
+
+    // Every part the schema allows.
+    fn all_ratings(&self) -> RangedPart<'a> {
+        RangedPart { ranges: self.categories.iter().map(|&(name, range)| (name, RangeSet::from(range))).collect() }
+    }
+}
+
+// The ratings of a part, in the order of the categories of its schema.
+#[derive(Clone)]
+struct Part<'a> {
+    ratings: Vec<(&'a str, i64)>,
+}
+
+impl Display for Part<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let ratings: Vec<String> = self.ratings.iter().map(|(name, rating)| format!("{name}={rating}")).collect();
+        write!(f, "{{{}}}", ratings.join(","))
+    }
+}
+
+impl Part<'_> {
+    fn rating(&self, category: &str) -> i64 {
+        self.ratings.iter()
+            .find(|(name, _)| *name == category)
+            .map(|&(_, rating)| rating)
+            .unwrap_or_else(|| panic!("part has no {category} rating"))
+    }
+
+    fn sum_all(&self) -> i64 {
+        self.ratings.iter().map(|(_, rating)| rating).sum()
+    }
+}
+
+struct Workflow<'a> {
+    name: &'a str,
+    rules: Vec<Rule<'a>>,
 }
 
-enum PartAccessor { X, M, A, S }
+impl<'a> Workflow<'a> {
+    // The first rule matching the part, with its index in the workflow.
+    fn matching_rule(&self, part: &Part) -> Option<(usize, &Rule<'a>)> {
+        self.rules.iter().enumerate().find(|(_, rule)| rule.execute_rule(part).is_some())
+    }
+}
+
+enum Rule<'a> {
+    Conditional(Condition<'a>, RuleResult<'a>),
+    Immediate(RuleResult<'a>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum Operator {
+    Higher,
+    HigherOrEqual,
+    Lower,
+    LowerOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let operator = match self {
+            Operator::Higher => ">",
+            Operator::HigherOrEqual => ">=",
+            Operator::Lower => "<",
+            Operator::LowerOrEqual => "<=",
+            Operator::Equal => "==",
+            Operator::NotEqual => "!=",
+        };
+        write!(f, "{operator}")
+    }
+}
 
-impl FromStr for PartAccessor {
+impl FromStr for Operator {
     type Err = ();
 
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         match s {
-            "x" => Ok(PartAccessor::X),
-            "m" => Ok(PartAccessor::M),
-            "a" => Ok(PartAccessor::A),
-            "s" => Ok(PartAccessor::S),
+            ">" => Ok(Operator::Higher),
+            ">=" => Ok(Operator::HigherOrEqual),
+            "<" => Ok(Operator::Lower),
+            "<=" => Ok(Operator::LowerOrEqual),
+            "==" => Ok(Operator::Equal),
+            "!=" => Ok(Operator::NotEqual),
             _ => Err(()),
         }
     }
 }
 
-impl Part {
-    fn access(&self, accessor: &PartAccessor) -> u64 {
-        match accessor {
-            PartAccessor::X => self.x,
-            PartAccessor::M => self.m,
-            PartAccessor::A => self.a,
-            PartAccessor::S => self.s,
+impl Operator {
+    fn compare(&self, rating: i64, value: i64) -> bool {
+        match self {
+            Operator::Higher => rating > value,
+            Operator::HigherOrEqual => rating >= value,
+            Operator::Lower => rating < value,
+            Operator::LowerOrEqual => rating <= value,
+            Operator::Equal => rating == value,
+            Operator::NotEqual => rating != value,
+        }
+    }
+
+    // The operator matching exactly the ratings this one doesn't match.
+    fn negate(&self) -> Operator {
+        match self {
+            Operator::Higher => Operator::LowerOrEqual,
+            Operator::HigherOrEqual => Operator::Lower,
+            Operator::Lower => Operator::HigherOrEqual,
+            Operator::LowerOrEqual => Operator::Higher,
+            Operator::Equal => Operator::NotEqual,
+            Operator::NotEqual => Operator::Equal,
         }
     }
 
-    fn sum_all(&self) -> u64 {
-        self.x + self.m + self.a + self.s
+    // Every rating that compares true against `value`.
+    fn matching(&self, value: i64) -> RangeSet {
+        let below = value.checked_sub(1).map(|end| Range::new(i64::MIN, end));
+        let above = value.checked_add(1).map(|start| Range::new(start, i64::MAX));
+
+        let ranges = match self {
+            Operator::Higher => vec![above],
+            Operator::HigherOrEqual => vec![Some(Range::new(value, i64::MAX))],
+            Operator::Lower => vec![below],
+            Operator::LowerOrEqual => vec![Some(Range::new(i64::MIN, value))],
+            Operator::Equal => vec![Some(Range::new(value, value))],
+            Operator::NotEqual => vec![below, above],
+        };
+
+        RangeSet { ranges: ranges.into_iter().flatten().collect() }
     }
 }
 
-struct Workflow<'a> {
-    name: &'a str,
-    rules: Vec<Rule<'a>>,
+// The comparison of a conditional rule, like `s<1351`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+struct Condition<'a> {
+    category: &'a str,
+    operator: Operator,
+    value: i64,
 }
 
-impl Workflow<'_> {
-    fn execute(&self, part: &Part) -> &RuleResult {
-        for rule in self.rules.iter() {
-            match rule.execute_rule(part) {
-                Some(result) => return result,
-                None => continue,
-            }
-        }
-        &RuleResult::Result(Result::Accept)
+impl Display for Condition<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}{}", self.category, self.operator, self.value)
     }
 }
 
-enum Rule<'a> {
-    HigherThan(PartAccessor, u64, RuleResult<'a>),
-    LowerThan(PartAccessor, u64, RuleResult<'a>),
-    Immediate(RuleResult<'a>),
+impl<'a> Condition<'a> {
+    fn matches(&self, part: &Part) -> bool {
+        self.operator.compare(part.rating(self.category), self.value)
+    }
+
+    // Splits the ranges into the parts that match the condition and the parts that don't.
+    fn split(&self, ranged_part: RangedPart<'a>) -> (Option<RangedPart<'a>>, Option<RangedPart<'a>>) {
+        let ranges = ranged_part.ranges(self.category);
+        let matched = ranges.intersection(&self.operator.matching(self.value));
+        let rest = ranges.intersection(&self.operator.negate().matching(self.value));
+
+        (ranged_part.with_ranges(self.category, matched), ranged_part.with_ranges(self.category, rest))
+    }
 }
 
-impl Rule<'_> {
-    fn execute_rule(&self, part: &Part) -> Option<&RuleResult> {
+impl<'a> Rule<'a> {
+    fn execute_rule(&self, part: &Part) -> Option<&RuleResult<'_>> {
+        match self.condition() {
+            Some(condition) if !condition.matches(part) => None,
+            _ => Some(self.result()),
+        }
+    }
+
+    fn condition(&self) -> Option<Condition<'a>> {
         match self {
-            Rule::HigherThan(part_accessor, value, result) =>
-                if part.access(part_accessor) > *value {
-                    Some(result)
-                } else {
-                    None
-                },
-            Rule::LowerThan(part_accessor, value, result) =>
-                if part.access(part_accessor) < *value {
-                    Some(result)
-                } else {
-                    None
-                },
-            Rule::Immediate(r) => Some(r),
+            Rule::Conditional(condition, _) => Some(*condition),
+            Rule::Immediate(_) => None,
+        }
+    }
+
+    fn result(&self) -> &RuleResult<'a> {
+        match self {
+            Rule::Conditional(_, result) | Rule::Immediate(result) => result,
+        }
+    }
+
+    // Splits the ranges into the parts this rule matches and the parts that go on to the next rule.
+    fn split(&self, ranged_part: RangedPart<'a>) -> (Option<RangedPart<'a>>, Option<RangedPart<'a>>) {
+        match self.condition() {
+            Some(condition) => condition.split(ranged_part),
+            None => (Some(ranged_part), None),
         }
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 enum RuleResult<'a> {
     Result(Result),
     Redirect(&'a str),
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 enum Result {
     Accept,
     Reject,
 }
 
-#[derive(Clone, Copy)]
-struct RangedPart {
-    x: Range,
-    m: Range,
-    a: Range,
-    s: Range,
+impl Display for Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Result::Accept => write!(f, "A"),
+            Result::Reject => write!(f, "R"),
+        }
+    }
+}
+
+// The ratings parts can have in every category of their schema.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct RangedPart<'a> {
+    ranges: Vec<(&'a str, RangeSet)>,
 }
 
-impl RangedPart {
-    fn new(x: Range, m: Range, a: Range, s: Range) -> Self {
-        RangedPart { x, m, a, s }
+impl Display for RangedPart<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let ranges: Vec<String> = self.ranges.iter().map(|(name, ranges)| format!("{name}={ranges}")).collect();
+        write!(f, "{}", ranges.join(" "))
     }
+}
 
-    fn new_all_same(range: Range) -> Self {
-        RangedPart::new(range, range, range, range)
+impl<'a> RangedPart<'a> {
+    fn multiply_all(&self) -> u128 {
+        self.ranges.iter().map(|(_, ranges)| ranges.size()).product()
     }
 
-    fn multiply_all(&self) -> u64 {
-        self.x.size() * self.m.size() * self.a.size() * self.s.size()
+    fn ranges(&self, category: &str) -> &RangeSet {
+        self.ranges.iter()
+            .find(|(name, _)| *name == category)
+            .map(|(_, ranges)| ranges)
+            .unwrap_or_else(|| panic!("unknown category {category}"))
     }
 
-    fn access_part(&mut self, accessor: &PartAccessor) -> &mut Range {
-        match accessor {
-            PartAccessor::X => &mut self.x,
-            PartAccessor::M => &mut self.m,
-            PartAccessor::A => &mut self.a,
-            PartAccessor::S => &mut self.s,
+    // The same parts with other ratings in `category`, or None if no rating is left in it.
+    fn with_ranges(&self, category: &str, ranges: RangeSet) -> Option<RangedPart<'a>> {
+        if ranges.is_empty() {
+            return None;
         }
+
+        let mut ranged_part = self.clone();
+        for (name, current) in &mut ranged_part.ranges {
+            if *name == category {
+                *current = ranges.clone();
+            }
+        }
+        Some(ranged_part)
     }
+}
 
-    fn split_at(&self, accessor: &PartAccessor, value: u64, upperbound: bool) -> Option<(RangedPart, RangedPart)> {
-        let mut ranged_part_left = self.clone();
-        let mut ranged_part_right = self.clone();
+// Disjoint ranges, sorted by their start.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct RangeSet {
+    ranges: Vec<Range>,
+}
+
+impl From<Range> for RangeSet {
+    fn from(range: Range) -> Self {
+        RangeSet { ranges: vec![range] }
+    }
+}
+
+impl Display for RangeSet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let ranges: Vec<String> = self.ranges.iter().map(Range::to_string).collect();
+        write!(f, "{}", ranges.join(","))
+    }
+}
 
-        let range = ranged_part_left.access_part(accessor);
-        let (left, right) = range.split_at(value, upperbound)?;
+impl RangeSet {
+    fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    fn size(&self) -> u128 {
+        self.ranges.iter().map(Range::size).sum()
+    }
 
-        *ranged_part_left.access_part(accessor) = left;
-        *ranged_part_right.access_part(accessor) = right;
+    fn intersection(&self, other: &RangeSet) -> RangeSet {
+        let mut ranges: Vec<Range> = self.ranges.iter()
+            .flat_map(|range| other.ranges.iter().filter_map(|other| range.intersection(other)))
+            .collect();
+        ranges.sort_by_key(|range| range.start);
 
-        Some((ranged_part_left, ranged_part_right))
+        RangeSet { ranges }
     }
 }
 
-#[derive(Clone, Copy)]
+// The ratings from `start` to `end`, both included.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 struct Range {
-    start: u64,
-    end: u64,
+    start: i64,
+    end: i64,
+}
+
+impl Display for Range {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..={}", self.start, self.end)
+    }
 }
 
 impl Range {
-    fn new(start: u64, end: u64) -> Self {
+    fn new(start: i64, end: i64) -> Self {
         Range { start, end }
     }
 
-    fn size(&self) -> u64 {
-        self.end - self.start + 1
+    fn size(&self) -> u128 {
+        (self.end as i128 - self.start as i128 + 1) as u128
     }
 
-    fn split_at(&self, value: u64, upperbound: bool) -> Option<(Range, Range)> {
-        let right_value = if upperbound { value } else { value - 1 };
-        let left_value = if upperbound { value + 1 } else { value };
+    fn intersection(&self, other: &Range) -> Option<Range> {
+        let (start, end) = (self.start.max(other.start), self.end.min(other.end));
+        (start <= end).then(|| Range::new(start, end))
+    }
+}
 
-        if right_value <= self.start || left_value >= self.end {
-            return None;
+// Workflow systems that can't be run: a part is sent to a missing workflow, or to a workflow it already went through.
+#[derive(Debug, PartialEq, Eq)]
+enum WorkflowError<'a> {
+    MissingWorkflow(&'a str),
+    Cycle(&'a str),
+}
+
+impl Display for WorkflowError<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorkflowError::MissingWorkflow(name) => write!(f, "workflow `{name}` doesn't exist"),
+            WorkflowError::Cycle(name) => write!(f, "workflow `{name}` is part of a cycle"),
         }
-        Some((Range::new(self.start, right_value), Range::new(left_value, self.end)))
     }
 }
 
 struct System<'a> {
+    schema: Schema<'a>,
     workflows: HashMap<&'a str, Workflow<'a>>,
-    parts: Vec<Part>,
+    parts: Vec<Part<'a>>,
 }
 
-impl System<'_> {
+impl<'a> System<'a> {
     fn execute_workflow(&self, part: &Part) -> Result {
-        let start = self.workflows.get("in").expect("workflow in has to exist");
-
-        fn execute_workflow_inner(system: &System, part: &Part, current: &Workflow) -> Result {
-            match current.execute(part) {
-                RuleResult::Result(result) => *result,
-                RuleResult::Redirect(name) => {
-                    let next = system.workflows.get(name).expect("system cannot contain unexisting workflow");
-                    execute_workflow_inner(system, part, next)
-                }
-            }
+        match self.explain(part).result() {
+            Ok(result) => *result,
+            Err(error) => panic!("cannot run the workflows: {error}"),
         }
-
-        execute_workflow_inner(self, part, start)
     }
 
-    fn sum_all_accepted(&self) -> u64 {
+    fn sum_all_accepted(&self) -> i64 {
         self.parts.iter()
             .filter(|part| self.execute_workflow(part) == Result::Accept)
             .map(|part| part.sum_all())
             .sum()
     }
 
-    fn get_accepted_in_range_rule_result(&self, rule_result: &RuleResult, ranged_part: RangedPart) -> u64 {
+    fn get_accepted_in_range_rule_result(&self, rule_result: &RuleResult, ranged_part: RangedPart<'a>) -> u128 {
         match rule_result {
             RuleResult::Result(Result::Accept) => ranged_part.multiply_all(),
             RuleResult::Result(Result::Reject) => 0,
@@ -203,36 +397,27 @@ impl System<'_> {
         }
     }
 
-    fn get_accepted_in_range(&self, workflow: &Workflow, mut ranged_part: RangedPart) -> u64 {
+    // The number of parts within the ranges accepted from `workflow` onwards, whatever the number of categories.
+    fn get_accepted_in_range(&self, workflow: &Workflow<'a>, ranged_part: RangedPart<'a>) -> u128 {
         let mut result = 0;
+        let mut remaining = Some(ranged_part);
+
         for rule in &workflow.rules {
-            match rule {
-                Rule::HigherThan(part, value, rule_result) => {
-                    if let Some((left_ranged_part, right_ranged_part)) = ranged_part.split_at(part, *value, true) {
-                        result += self.get_accepted_in_range_rule_result(rule_result, right_ranged_part);
-                        ranged_part = left_ranged_part;
-                    } else {
-                        result += self.get_accepted_in_range_rule_result(rule_result, ranged_part);
-                    }
-                }
-                Rule::LowerThan(part, value, rule_result) => {
-                    if let Some((left_ranged_part, right_ranged_part)) = ranged_part.split_at(part, *value, false) {
-                        result += self.get_accepted_in_range_rule_result(rule_result, left_ranged_part);
-                        ranged_part = right_ranged_part;
-                    } else {
-                        result += self.get_accepted_in_range_rule_result(rule_result, ranged_part);
-                    }
-                }
-                Rule::Immediate(rule_result) => {
-                    result += self.get_accepted_in_range_rule_result(rule_result, ranged_part);
-                }
+            let Some(ranged_part) = remaining else { break };
+            let (matched, rest) = rule.split(ranged_part);
+
+            if let Some(matched) = matched {
+                result += self.get_accepted_in_range_rule_result(rule.result(), matched);
             }
+            remaining = rest;
         }
-        result
+
+        // like `System::explain`, parts that no rule matches are accepted
+        result + remaining.map(|ranged_part| ranged_part.multiply_all()).unwrap_or(0)
     }
 }
 
-fn parse_rule_result(input: &str) -> RuleResult {
+fn parse_rule_result(input: &str) -> RuleResult<'_> {
     match input {
         "A" => RuleResult::Result(Result::Accept),
         "R" => RuleResult::Result(Result::Reject),
@@ -240,86 +425,259 @@ fn parse_rule_result(input: &str) -> RuleResult {
     }
 }
 
-fn parse_rule(input: &str) -> Rule {
+// `s>=1351`: a category of the schema, one of `>`, `>=`, `<`, `<=`, `==` or `!=` and a value.
+fn parse_condition<'a>(input: &str, schema: &Schema<'a>) -> Condition<'a> {
+    let is_operator = |c: char| "<>=!".contains(c);
+    let operator_start = input.find(is_operator).expect("condition has to contain an operator");
+    let value_start = input[operator_start..].find(|c| !is_operator(c)).expect("condition has to contain a value") + operator_start;
+
+    let (category, operator, value) = (&input[..operator_start], &input[operator_start..value_start], &input[value_start..]);
+    Condition {
+        category: schema.category(category).unwrap_or_else(|| panic!("unknown category {category}")),
+        operator: operator.parse().unwrap_or_else(|_| panic!("unknown operator {operator}")),
+        value: value.parse().expect("value has to be a number"),
+    }
+}
+
+fn parse_rule<'a>(input: &'a str, schema: &Schema<'a>) -> Rule<'a> {
     if let Some((condition_string, then_string)) = input.split_once(":") {
-        if let Some((variable, value)) = condition_string.split_once(">") {
-            let variable = variable.parse::<PartAccessor>().expect("variable has to be x, m, a or s");
-            let value = value.parse::<u64>().expect("value has to be a number");
-            Rule::HigherThan(variable, value, parse_rule_result(then_string))
-        } else if let Some((variable, value)) = condition_string.split_once("<") {
-            let variable = variable.parse::<PartAccessor>().expect("variable has to be x, m, a or s");
-            let value = value.parse::<u64>().expect("value has to be a number");
-            Rule::LowerThan(variable, value, parse_rule_result(then_string))
-        } else {
-            panic!("condition has to contain > or <")
-        }
+        Rule::Conditional(parse_condition(condition_string, schema), parse_rule_result(then_string))
     } else {
         Rule::Immediate(parse_rule_result(input))
     }
 }
 
-fn parse_workflow(input: &str) -> Workflow {
+fn parse_workflow<'a>(input: &'a str, schema: &Schema<'a>) -> Workflow<'a> {
     let (workflow_name, rules) = input.split_once("{").expect("workflow has to contain {");
-    let rules = rules.trim_end_matches("}").split(",").map(parse_rule).collect();
+    let rules = rules.trim_end_matches("}").split(",").map(|rule| parse_rule(rule, schema)).collect();
     Workflow { name: workflow_name, rules }
 }
 
-fn parse_part(input: &str) -> Part {
-    let mut x = 0;
-    let mut m = 0;
-    let mut a = 0;
-    let mut s = 0;
+fn parse_part<'a>(input: &str, schema: &Schema<'a>) -> Part<'a> {
+    let mut ratings: Vec<(&'a str, Option<i64>)> = schema.categories.iter().map(|&(name, _)| (name, None)).collect();
+
     input
         .trim_start_matches("{")
         .trim_end_matches("}")
         .split(",")
         .for_each(|part| {
             let (variable_name, value) = part.split_once("=").expect("part has to contain =");
-            let value = value.parse::<u64>().expect("value has to be a number");
-            match variable_name {
-                "x" => x = value,
-                "m" => m = value,
-                "a" => a = value,
-                "s" => s = value,
-                _ => panic!("unknown variable name"),
-            }
+            let (_, rating) = ratings.iter_mut()
+                .find(|(name, _)| *name == variable_name)
+                .unwrap_or_else(|| panic!("unknown category {variable_name}"));
+            *rating = Some(value.parse::<i64>().expect("value has to be a number"));
         });
 
-    Part { x, m, a, s }
+    let ratings = ratings.into_iter()
+        .map(|(name, rating)| (name, rating.unwrap_or_else(|| panic!("part has no {name} rating"))))
+        .collect();
+    Part { ratings }
 }
 
-fn parse_system(input: &str) -> System {
+fn parse_range(input: &str) -> Range {
+    let (start, end) = input.split_once("..=").expect("range has to contain ..=");
+    let range = Range::new(start.parse().expect("start has to be a number"), end.parse().expect("end has to be a number"));
+    assert!(range.start <= range.end, "range {input} is empty");
+    range
+}
+
+// `schema{x=1..=4000,m=1..=4000}`, the categories of the parts with the ratings allowed in each of them.
+fn parse_schema(input: &str) -> Schema<'_> {
+    let mut categories: Vec<(&str, Range)> = vec![];
+
+    for category in input.trim_start_matches("schema{").trim_end_matches("}").split(",") {
+        let (name, range) = category.split_once("=").expect("category has to contain =");
+        assert!(categories.iter().all(|&(other, _)| other != name), "category {name} is declared twice");
+        categories.push((name, parse_range(range)));
+    }
+
+    Schema { categories }
+}
+
+// An optional schema on the first line, the default x, m, a and s categories otherwise, then the workflows and the parts.
+fn parse_system(input: &str) -> System<'_> {
+    let mut lines = input.lines().peekable();
+    let schema = match lines.next_if(|line| line.starts_with("schema{")) {
+        Some(line) => parse_schema(line),
+        None => Schema::default(),
+    };
+
     let mut workflows = HashMap::new();
     let mut parts = Vec::new();
 
     let mut parsing_workflow = true;
 
-    for line in input.lines() {
+    for line in lines {
         if line.is_empty() {
             parsing_workflow = false;
             continue;
         }
         if parsing_workflow {
-            let workflow = parse_workflow(line);
+            let workflow = parse_workflow(line, &schema);
             workflows.insert(workflow.name, workflow);
         } else {
-            let part = parse_part(line);
+            let part = parse_part(line, &schema);
             parts.push(part);
         }
     }
 
-    System { workflows, parts }
+    System { schema, workflows, parts }
 }
 
-fn part_2(system: &System) -> u64 {
+fn part_2(system: &System) -> u128 {
     let in_workflow = system.workflows.get("in").expect("workflow in has to exist");
-    system.get_accepted_in_range(in_workflow, RangedPart::new_all_same(Range::new(1, 4000)))
+    system.get_accepted_in_range(in_workflow, system.schema.all_ratings())
 }
 
 fn main() {
     let input = include_str!("input.txt");
     let system = parse_system(input);
 
-    println!("Part 1: {}", system.sum_all_accepted());
-    println!("Part 2: {}", part_2(&system));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["validate"] => {
+            let diagnostics = validate::validate(&system);
+            if diagnostics.is_empty() {
+                println!("No problems found");
+            }
+            for diagnostic in diagnostics {
+                println!("{diagnostic}");
+            }
+        }
+        ["explain", parts @ ..] => {
+            let parts: Vec<Part> = if parts.is_empty() {
+                system.parts.clone()
+            } else {
+                parts.iter().map(|part| parse_part(part, &system.schema)).collect()
+            };
+
+            for part in &parts {
+                println!("{part}: {}", system.explain(part));
+            }
+        }
+        ["compile", format] => match compile::compile(&system) {
+            Ok(decision) => match *format {
+                "workflows" => print!("{}", decision.to_workflows(&system.schema)),
+                "dot" => print!("{}", decision.to_dot()),
+                "json" => println!("{}", decision.to_json()),
+                "ranges" => {
+                    for ranged_part in decision.accepted_ranges(&system.schema) {
+                        println!("{ranged_part}");
+                    }
+                }
+                _ => eprintln!("Unknown format {format}, expected workflows, dot, json or ranges"),
+            },
+            Err(error) => eprintln!("Could not compile the workflows: {error}"),
+        },
+        [] => {
+            println!("Part 1: {}", system.sum_all_accepted());
+            println!("Part 2: {}", part_2(&system));
+        }
+        _ => eprintln!("Usage: day-19 [validate | explain [<part>...] | compile <workflows | dot | json | ranges>]"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "px{a<2006:qkq,m>2090:A,rfg}
+pv{a>1716:R,A}
+lnx{m>1548:A,A}
+rfg{s<537:gd,x>2440:R,A}
+qs{s>3448:A,lnx}
+qkq{x<1416:A,crn}
+crn{x>2662:A,R}
+in{s<1351:px,qqz}
+qqz{s>2770:qs,m<1801:hdj,R}
+gd{a>3333:R,R}
+hdj{m>838:A,pv}
+
+{x=787,m=2655,a=1222,s=2876}
+{x=1679,m=44,a=2067,s=496}
+{x=2036,m=264,a=79,s=2244}
+{x=2461,m=1339,a=466,s=291}
+{x=2127,m=1623,a=2188,s=1013}";
+
+    #[test]
+    fn test_example() {
+        let system = parse_system(EXAMPLE);
+
+        assert_eq!(system.sum_all_accepted(), 19114);
+        assert_eq!(part_2(&system), 167409079868000);
+    }
+
+    // the parts matching the condition and the others, in a single category rated from 1 to 4000
+    fn split(condition: &str) -> String {
+        let schema = parse_schema("schema{x=1..=4000}");
+        let (matched, rest) = parse_condition(condition, &schema).split(schema.all_ratings());
+
+        let format = |ranged_part: Option<RangedPart>| ranged_part.map_or("none".to_string(), |ranged_part| ranged_part.to_string());
+        format!("{} / {}", format(matched), format(rest))
+    }
+
+    #[test]
+    fn test_split_at_edges() {
+        assert_eq!(split("x<=1"), "x=1..=1 / x=2..=4000");
+        assert_eq!(split("x<=4000"), "x=1..=4000 / none");
+        assert_eq!(split("x<1"), "none / x=1..=4000");
+        assert_eq!(split("x<0"), "none / x=1..=4000");
+        assert_eq!(split("x<5000"), "x=1..=4000 / none");
+        assert_eq!(split("x>=4000"), "x=4000..=4000 / x=1..=3999");
+        assert_eq!(split("x>-1"), "x=1..=4000 / none");
+    }
+
+    #[test]
+    fn test_equality_operators() {
+        assert_eq!(split("x==2000"), "x=2000..=2000 / x=1..=1999,2001..=4000");
+        assert_eq!(split("x!=2000"), "x=1..=1999,2001..=4000 / x=2000..=2000");
+        assert_eq!(split("x!=1"), "x=2..=4000 / x=1..=1");
+        assert_eq!(split("x==0"), "none / x=1..=4000");
+        assert_eq!(split("x!=0"), "x=1..=4000 / none");
+    }
+
+    #[test]
+    fn test_operators() {
+        let system = parse_system("in{x>=10:R,m<=5:R,a==7:R,s!=1:R,A}\n\n{x=9,m=6,a=8,s=1}\n{x=9,m=6,a=7,s=1}\n");
+
+        assert_eq!(system.sum_all_accepted(), 24);
+        assert_eq!(part_2(&system), 9 * 3995 * 3999);
+    }
+
+    #[test]
+    fn test_custom_schema() {
+        let system = parse_system("schema{weight=-50..=50,priority=1..=3}
+in{weight<0:R,priority==3:A,low}
+low{weight<=10:R,A}
+
+{weight=20,priority=1}
+{priority=3,weight=-5}
+{weight=5,priority=3}");
+
+        assert_eq!(system.schema.to_string(), "schema{weight=-50..=50,priority=1..=3}");
+        assert_eq!(system.parts[1].to_string(), "{weight=-5,priority=3}");
+        assert_eq!(system.sum_all_accepted(), 21 + 8);
+        // weight from 0 to 50 with priority 3, and from 11 to 50 with priority 1 or 2
+        assert_eq!(part_2(&system), 51 + 40 * 2);
+    }
+
+    // Part 2 used to send every part to a rule's result when the rule couldn't split the ranges, and to keep
+    // going through the rules after one matched every remaining part.
+    #[test]
+    fn test_part_2_range_counting_regressions() {
+        // x>4000 matches nothing, so nothing is accepted
+        assert_eq!(part_2(&parse_system("in{x>4000:A,R}\n")), 0);
+        // x<5000 matches everything, so the last rule is never reached
+        assert_eq!(part_2(&parse_system("in{x<5000:R,A}\n")), 0);
+        // like the parts of part 1, the parts no rule matches are accepted
+        assert_eq!(part_2(&parse_system("in{x>2000:R}\n")), 2000 * 4000 * 4000 * 4000);
+    }
+
+    #[test]
+    fn test_rules_outside_of_the_ranges() {
+        // x>4000 never matches, x>1 matches everything but x=1
+        let system = parse_system("in{x>4000:A,x>1:R,A}\n");
+
+        assert_eq!(part_2(&system), 4000 * 4000 * 4000);
+    }
 }
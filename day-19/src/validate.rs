@@ -0,0 +1,320 @@
+// Static checks for a workflow file, run before any part goes through it.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+use crate::{RangedPart, Result, RuleResult, Schema, System, Workflow};
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Diagnostic<'a> {
+    MissingStart,
+    // `rule` is the index of the rule in its workflow
+    MissingWorkflow { workflow: &'a str, rule: usize, target: &'a str },
+    // every workflow in the cycle, sorted by name
+    Cycle(Vec<&'a str>),
+    Unreachable(&'a str),
+    // earlier rules in the workflow already match every part this rule could match
+    DeadRule { workflow: &'a str, rule: usize },
+    ConstantWorkflow { workflow: &'a str, result: Result },
+}
+
+impl Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Diagnostic::MissingStart => write!(f, "there is no `in` workflow to start from"),
+            Diagnostic::MissingWorkflow { workflow, rule, target } => {
+                write!(f, "{workflow}: rule #{} sends parts to `{target}`, which doesn't exist", rule + 1)
+            }
+            Diagnostic::Cycle(workflows) => write!(f, "{} send parts to each other in a cycle", workflows.join(", ")),
+            Diagnostic::Unreachable(workflow) => write!(f, "{workflow}: no part can ever reach it from `in`"),
+            Diagnostic::DeadRule { workflow, rule } => {
+                write!(f, "{workflow}: rule #{} never fires, earlier rules match every part it would match", rule + 1)
+            }
+            Diagnostic::ConstantWorkflow { workflow, result } => {
+                let result = if *result == Result::Accept { "accepts" } else { "rejects" };
+                write!(f, "{workflow}: always {result} every part that reaches it")
+            }
+        }
+    }
+}
+
+fn redirects<'a, 'b>(workflow: &'b Workflow<'a>) -> impl Iterator<Item=(usize, &'a str)> + 'b {
+    workflow.rules.iter().enumerate().filter_map(|(index, rule)| match rule.result() {
+        RuleResult::Redirect(name) => Some((index, *name)),
+        RuleResult::Result(_) => None,
+    })
+}
+
+pub(crate) fn validate<'a>(system: &System<'a>) -> Vec<Diagnostic<'a>> {
+    let mut names: Vec<&'a str> = system.workflows.keys().copied().collect();
+    names.sort();
+
+    let mut diagnostics = vec![];
+    if !system.workflows.contains_key("in") {
+        diagnostics.push(Diagnostic::MissingStart);
+    }
+
+    for &name in &names {
+        let workflow = &system.workflows[name];
+
+        for (rule, target) in redirects(workflow) {
+            if !system.workflows.contains_key(target) {
+                diagnostics.push(Diagnostic::MissingWorkflow { workflow: name, rule, target });
+            }
+        }
+
+        diagnostics.extend(dead_rules(&system.schema, workflow).into_iter().map(|rule| Diagnostic::DeadRule { workflow: name, rule }));
+
+        let trivial = matches!(workflow.rules[..], [ref rule] if matches!(rule.result(), RuleResult::Result(_)));
+        if !trivial {
+            if let Some(result) = constant_result(system, name) {
+                diagnostics.push(Diagnostic::ConstantWorkflow { workflow: name, result });
+            }
+        }
+    }
+
+    diagnostics.extend(cycles(system, &names).into_iter().map(Diagnostic::Cycle));
+
+    if system.workflows.contains_key("in") {
+        let reachable = reachable_from(system, "in");
+        diagnostics.extend(names.iter().filter(|name| !reachable.contains(*name)).map(|name| Diagnostic::Unreachable(name)));
+    }
+
+    diagnostics
+}
+
+fn dead_rules<'a>(schema: &Schema<'a>, workflow: &Workflow<'a>) -> Vec<usize> {
+    let mut dead = vec![];
+    let mut remaining = Some(schema.all_ratings());
+
+    for (index, rule) in workflow.rules.iter().enumerate() {
+        let (matched, rest) = match remaining {
+            Some(ranged_part) => rule.split(ranged_part),
+            None => (None, None),
+        };
+
+        if matched.is_none() {
+            dead.push(index);
+        }
+        remaining = rest;
+    }
+
+    dead
+}
+
+// The result every part reaching the workflow ends up with, if they all end up with the same one.
+// Workflows that can reach a missing workflow or a cycle are never constant.
+fn constant_result(system: &System, name: &str) -> Option<Result> {
+    let mut results = HashSet::new();
+    let mut visiting = vec![];
+
+    if !collect_results(system, name, system.schema.all_ratings(), &mut visiting, &mut results) {
+        return None;
+    }
+
+    match results.into_iter().collect::<Vec<_>>()[..] {
+        [result] => Some(result),
+        _ => None,
+    }
+}
+
+// Adds the results reachable from the workflow to `results`, returning false if a missing workflow or a cycle
+// is found or if both results were already found, since then there is nothing left to learn.
+fn collect_results<'a>(
+    system: &System<'a>,
+    name: &'a str,
+    ranged_part: RangedPart<'a>,
+    visiting: &mut Vec<&'a str>,
+    results: &mut HashSet<Result>,
+) -> bool {
+    let Some(workflow) = system.workflows.get(name) else { return false };
+    if visiting.contains(&name) {
+        return false;
+    }
+    visiting.push(name);
+
+    let mut remaining = Some(ranged_part);
+    for rule in &workflow.rules {
+        let Some(ranged_part) = remaining else { break };
+        let (matched, rest) = rule.split(ranged_part);
+        remaining = rest;
+
+        let Some(matched) = matched else { continue };
+        let complete = match rule.result() {
+            RuleResult::Result(result) => {
+                results.insert(*result);
+                results.len() < 2
+            }
+            RuleResult::Redirect(next) => collect_results(system, next, matched, visiting, results),
+        };
+        if !complete {
+            return false;
+        }
+    }
+
+    if remaining.is_some() {
+        results.insert(Result::Accept);
+    }
+
+    visiting.pop();
+    results.len() < 2
+}
+
+fn reachable_from<'a>(system: &System<'a>, start: &'a str) -> HashSet<&'a str> {
+    let mut reachable = HashSet::from([start]);
+    let mut stack = vec![start];
+
+    while let Some(name) = stack.pop() {
+        let Some(workflow) = system.workflows.get(name) else { continue };
+        for (_, next) in redirects(workflow) {
+            if reachable.insert(next) {
+                stack.push(next);
+            }
+        }
+    }
+
+    reachable
+}
+
+// Strongly connected components of the redirect graph that contain a cycle, with Tarjan's algorithm.
+fn cycles<'a>(system: &System<'a>, names: &[&'a str]) -> Vec<Vec<&'a str>> {
+    struct Tarjan<'a, 'b> {
+        system: &'b System<'a>,
+        index: HashMap<&'a str, usize>,
+        low_link: HashMap<&'a str, usize>,
+        stack: Vec<&'a str>,
+        on_stack: HashSet<&'a str>,
+        cycles: Vec<Vec<&'a str>>,
+    }
+
+    impl<'a> Tarjan<'a, '_> {
+        fn visit(&mut self, name: &'a str) {
+            let next_index = self.index.len();
+            self.index.insert(name, next_index);
+            self.low_link.insert(name, next_index);
+            self.stack.push(name);
+            self.on_stack.insert(name);
+
+            let targets: Vec<&'a str> = redirects(&self.system.workflows[name])
+                .map(|(_, target)| target)
+                .filter(|target| self.system.workflows.contains_key(target))
+                .collect();
+
+            for &target in &targets {
+                if !self.index.contains_key(target) {
+                    self.visit(target);
+                    self.low_link.insert(name, self.low_link[name].min(self.low_link[target]));
+                } else if self.on_stack.contains(target) {
+                    self.low_link.insert(name, self.low_link[name].min(self.index[target]));
+                }
+            }
+
+            if self.low_link[name] == self.index[name] {
+                let mut component = BTreeSet::new();
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack.remove(member);
+                    component.insert(member);
+                    if member == name {
+                        break;
+                    }
+                }
+
+                if component.len() > 1 || targets.contains(&name) {
+                    self.cycles.push(component.into_iter().collect());
+                }
+            }
+        }
+    }
+
+    let mut tarjan = Tarjan {
+        system,
+        index: HashMap::new(),
+        low_link: HashMap::new(),
+        stack: vec![],
+        on_stack: HashSet::new(),
+        cycles: vec![],
+    };
+
+    for &name in names {
+        if !tarjan.index.contains_key(name) {
+            tarjan.visit(name);
+        }
+    }
+
+    tarjan.cycles.sort();
+    tarjan.cycles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_system;
+
+    const EXAMPLE: &str = "px{a<2006:qkq,m>2090:A,rfg}
+pv{a>1716:R,A}
+lnx{m>1548:A,A}
+rfg{s<537:gd,x>2440:R,A}
+qs{s>3448:A,lnx}
+qkq{x<1416:A,crn}
+crn{x>2662:A,R}
+in{s<1351:px,qqz}
+qqz{s>2770:qs,m<1801:hdj,R}
+gd{a>3333:R,R}
+hdj{m>838:A,pv}
+";
+
+    #[test]
+    fn test_example() {
+        let system = parse_system(EXAMPLE);
+
+        assert_eq!(validate(&system), vec![
+            Diagnostic::ConstantWorkflow { workflow: "gd", result: Result::Reject },
+            Diagnostic::ConstantWorkflow { workflow: "lnx", result: Result::Accept },
+            Diagnostic::ConstantWorkflow { workflow: "qs", result: Result::Accept },
+        ]);
+    }
+
+    #[test]
+    fn test_broken_system() {
+        let system = parse_system("in{x>10:a,m<5:b,R}
+a{x>5:A,x>100:R,R}
+b{s<4001:c,A}
+c{d}
+e{A}
+f{a>1:g,R}
+g{m>4000:R,f}
+");
+
+        assert_eq!(validate(&system), vec![
+            Diagnostic::DeadRule { workflow: "a", rule: 1 },
+            Diagnostic::DeadRule { workflow: "b", rule: 1 },
+            Diagnostic::MissingWorkflow { workflow: "c", rule: 0, target: "d" },
+            Diagnostic::DeadRule { workflow: "g", rule: 0 },
+            Diagnostic::Cycle(vec!["f", "g"]),
+            Diagnostic::Unreachable("e"),
+            Diagnostic::Unreachable("f"),
+            Diagnostic::Unreachable("g"),
+        ]);
+    }
+
+    #[test]
+    fn test_self_cycle_and_missing_start() {
+        let system = parse_system("loop{x>2000:loop,A}\n");
+
+        assert_eq!(validate(&system), vec![Diagnostic::MissingStart, Diagnostic::Cycle(vec!["loop"])]);
+        assert_eq!(
+            Diagnostic::Cycle(vec!["f", "g"]).to_string(),
+            "f, g send parts to each other in a cycle",
+        );
+    }
+
+    #[test]
+    fn test_custom_schema() {
+        let system = parse_system("schema{weight=-50..=50,priority=1..=3}\nin{priority>3:R,weight!=0:A,weight==0:R,A}\n");
+
+        assert_eq!(validate(&system), vec![
+            Diagnostic::DeadRule { workflow: "in", rule: 0 },
+            Diagnostic::DeadRule { workflow: "in", rule: 3 },
+        ]);
+    }
+}
@@ -16,6 +16,8 @@
 // send low pulse to broadcaster module
 
 use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 use std::time::Instant;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -24,6 +26,27 @@ enum Pulse {
     High,
 }
 
+impl Display for Pulse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pulse::Low => write!(f, "low"),
+            Pulse::High => write!(f, "high"),
+        }
+    }
+}
+
+impl FromStr for Pulse {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(Pulse::Low),
+            "high" => Ok(Pulse::High),
+            _ => Err(()),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 enum ModuleType {
     Broadcaster,
@@ -33,12 +56,13 @@ enum ModuleType {
 }
 
 #[derive(Clone, Debug)]
-struct Module {
+struct Module<'a> {
+    name: &'a str,
     connected_to: Vec<usize>,
     module_type: ModuleType,
 }
 
-impl Module {
+impl Module<'_> {
     fn pulse(&mut self, pulse: Pulse, from_module: usize) -> Option<Pulse> {
         match &mut self.module_type {
             ModuleType::Broadcaster => Some(pulse),
@@ -66,193 +90,357 @@ impl Module {
     }
 }
 
+// A pulse going from one module to another. `press` counts the button presses from 1, and `tick` the steps since
+// the button was pressed: the pulses a module sends when it receives a pulse of tick n have tick n + 1.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct PulseEvent<'a> {
+    from: &'a str,
+    to: &'a str,
+    pulse: Pulse,
+    press: u64,
+    tick: u64,
+}
+
+impl Display for PulseEvent<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -{}-> {}", self.from, self.pulse, self.to)
+    }
+}
+
+impl PulseEvent<'_> {
+    fn is_received_by(&self, module: &str, pulse: Pulse) -> bool {
+        self.to == module && self.pulse == pulse
+    }
+
+    fn is_sent_by(&self, module: &str, pulse: Pulse) -> bool {
+        self.from == module && self.pulse == pulse
+    }
+}
+
 #[derive(Clone)]
-struct ModuleConfiguration {
+struct ModuleConfiguration<'a> {
     broadcaster_id: usize,
-    modules: Vec<Module>,
+    modules: Vec<Module<'a>>,
+    // the number of times the button was pressed
+    presses: u64,
 }
 
-impl ModuleConfiguration {
-    fn press_button(&mut self) -> (u64, u64) {
-        let mut high = 0;
-        let mut low = 0;
-        let mut stack = VecDeque::new();
-        stack.push_back((self.broadcaster_id, Pulse::Low, self.broadcaster_id));
+impl<'a> ModuleConfiguration<'a> {
+    // The pulses sent after pressing the button once, simulated as they are consumed.
+    fn press_button(&mut self) -> Pulses<'_, 'a> {
+        self.presses += 1;
+        let queue = VecDeque::from([(None, self.broadcaster_id, Pulse::Low, 0)]);
 
-        while let Some((module_id, pulse, from_id)) = stack.pop_front() {
-            let module = &mut self.modules[module_id];
+        Pulses { press: self.presses, configuration: self, queue }
+    }
 
-            if pulse == Pulse::High {
-                high += 1;
-            } else {
-                low += 1;
+    // Presses the button at most `max_presses` times, until `stop` returns true for a pulse, and returns that pulse.
+    // The pulses sent after it in the same press are never simulated.
+    fn press_until(&mut self, max_presses: u64, mut stop: impl FnMut(&PulseEvent<'a>) -> bool) -> Option<PulseEvent<'a>> {
+        for _ in 0..max_presses {
+            if let Some(event) = self.press_button().find(|event| stop(event)) {
+                return Some(event);
             }
+        }
 
-            if let Some(pulse) = module.pulse(pulse, from_id) {
-                for connected_to_id in &module.connected_to {
-                    stack.push_back((*connected_to_id, pulse, module_id));
-                }
+        None
+    }
+}
+
+// Dropping it before the end leaves the pulses still on their way unprocessed.
+struct Pulses<'c, 'a> {
+    configuration: &'c mut ModuleConfiguration<'a>,
+    press: u64,
+    // the module sending the pulse (None for the button), the one receiving it, the pulse and its tick
+    queue: VecDeque<(Option<usize>, usize, Pulse, u64)>,
+}
+
+impl<'a> Iterator for Pulses<'_, 'a> {
+    type Item = PulseEvent<'a>;
+
+    fn next(&mut self) -> Option<PulseEvent<'a>> {
+        let (from_id, module_id, pulse, tick) = self.queue.pop_front()?;
+        let from = from_id.map_or("button", |from_id| self.configuration.modules[from_id].name);
+        let module = &mut self.configuration.modules[module_id];
+
+        // only the broadcaster receives pulses from the button, and it doesn't remember where they come from
+        if let Some(sent) = module.pulse(pulse, from_id.unwrap_or(module_id)) {
+            for connected_to_id in &module.connected_to {
+                self.queue.push_back((Some(module_id), *connected_to_id, sent, tick + 1));
             }
         }
 
-        (high, low)
+        Some(PulseEvent { from, to: module.name, pulse, press: self.press, tick })
     }
 }
 
 fn part1(mut module_configuration: ModuleConfiguration) -> u64 {
     let (mut high, mut low) = (0, 0);
-    for _ in 0..1000 {
-        let (new_high, new_low) = module_configuration.press_button();
-        high += new_high;
-        low += new_low;
-    }
+    module_configuration.press_until(1000, |event| {
+        match event.pulse {
+            Pulse::High => high += 1,
+            Pulse::Low => low += 1,
+        }
+        false
+    });
 
     high * low
 }
 
-fn lcm(x: u64, y: u64) -> u64 {
-    let max = u64::max(x, y);
-    let min = u64::min(x, y);
-    let mut lcm = max;
-    while lcm % min != 0 {
-        lcm += max;
+const MAX_PRESSES: u64 = 100_000;
+
+// How many high pulses of each input of the conjunction are recorded to find its cycle.
+const CYCLE_SAMPLES: usize = 3;
+
+// Why part 2 can't be solved from the cycles of the inputs of the conjunction sending pulses to `rx`.
+#[derive(Debug, Eq, PartialEq)]
+enum StructureError<'a> {
+    NoFeeder,
+    SeveralFeeders(Vec<&'a str>),
+    NotAConjunction(&'a str),
+    TooFewHighPulses { input: &'a str, presses: u64 },
+    // the input sends more than one high pulse in the press, or doesn't send a low pulse after it
+    NotAPulse { input: &'a str, press: u64 },
+    IrregularCycle { input: &'a str, presses: Vec<u64> },
+    CyclesNeverMatch,
+    // the press where the cycles line up doesn't fit in a u64
+    TooLarge,
+}
+
+impl Display for StructureError<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StructureError::NoFeeder => write!(f, "no module sends pulses to rx"),
+            StructureError::SeveralFeeders(feeders) => write!(f, "rx receives pulses from {}, not from a single conjunction", feeders.join(", ")),
+            StructureError::NotAConjunction(feeder) => write!(f, "{feeder} sends pulses to rx but is not a conjunction"),
+            StructureError::TooFewHighPulses { input, presses } => {
+                write!(f, "{input} sent fewer than {CYCLE_SAMPLES} high pulses in {presses} presses")
+            }
+            StructureError::NotAPulse { input, press } => {
+                write!(f, "{input} doesn't send a single high pulse followed by a low one at press {press}")
+            }
+            StructureError::IrregularCycle { input, presses } => {
+                let presses: Vec<String> = presses.iter().map(u64::to_string).collect();
+                write!(f, "{input} sends high pulses at presses {}, which are not evenly spaced", presses.join(", "))
+            }
+            StructureError::CyclesNeverMatch => write!(f, "the inputs never send high pulses during the same press"),
+            StructureError::TooLarge => write!(f, "the inputs only send high pulses during the same press after more than {} presses", u64::MAX),
+        }
     }
-    return lcm;
 }
 
-fn lcm_vec(vec: &[u64]) -> u64 {
-    vec.iter().fold(1, |acc, x| lcm(acc, *x))
+fn gcd(x: i128, y: i128) -> i128 {
+    if y == 0 { x.abs() } else { gcd(y, x % y) }
 }
 
-fn binary_counter_group(start: usize, module_configuration: &ModuleConfiguration) -> Vec<usize> {
-    let mut group = vec![];
-    let mut stack = vec![start];
+// The smallest non-negative n with n = remainder (mod modulus) for every congruence, with the modulus of the
+// solution. The moduli don't have to be coprime.
+fn crt(congruences: &[(u64, u64)]) -> Result<(u64, u64), StructureError<'static>> {
+    let (mut remainder, mut modulus) = (0i128, 1i128);
 
-    while let Some(module_id) = stack.pop() {
-        let module = &module_configuration.modules[module_id];
-        if let ModuleType::FlipFlop(_) = &module.module_type {
-            group.push(module_id);
-            for connected_to_id in &module.connected_to {
-                stack.push(*connected_to_id);
-            }
+    for &(other_remainder, other_modulus) in congruences {
+        let (other_remainder, other_modulus) = (other_remainder as i128, other_modulus as i128);
+        let divisor = gcd(modulus, other_modulus);
+        if (other_remainder - remainder) % divisor != 0 {
+            return Err(StructureError::CyclesNeverMatch);
         }
+
+        let reduced_modulus = other_modulus / divisor;
+        let new_modulus = modulus.checked_mul(reduced_modulus)
+            .filter(|&new_modulus| u64::try_from(new_modulus).is_ok())
+            .ok_or(StructureError::TooLarge)?;
+
+        // solves remainder + modulus * k = other_remainder (mod other_modulus) for k, both factors are below
+        // the reduced modulus, which fits in a u64, so their product fits in a u128
+        let difference = ((other_remainder - remainder) / divisor).rem_euclid(reduced_modulus) as u128;
+        let inverse = modular_inverse(modulus / divisor, reduced_modulus) as u128;
+        let k = (difference * inverse % reduced_modulus as u128) as i128;
+
+        remainder = (remainder + modulus * k).rem_euclid(new_modulus);
+        modulus = new_modulus;
     }
 
-    group
+    let too_large = |_| StructureError::TooLarge;
+    Ok((u64::try_from(remainder).map_err(too_large)?, u64::try_from(modulus).map_err(too_large)?))
 }
 
-fn get_binary_number(group: &[usize], module_configuration: &ModuleConfiguration) -> u64 {
-    let mut number = 0;
-    let mut mask = 1;
-    for &module_id in group {
-        let module = &module_configuration.modules[module_id];
+// The inverse of `x` modulo `modulus`, with the extended Euclidean algorithm. `x` and `modulus` have to be coprime.
+fn modular_inverse(x: i128, modulus: i128) -> i128 {
+    let (mut old_r, mut r) = (x.rem_euclid(modulus), modulus);
+    let (mut old_s, mut s) = (1, 0);
 
-        let is_connected_to_conjunction = module.connected_to.iter().any(|&connected_to|
-            matches!(module_configuration.modules[connected_to].module_type, ModuleType::Conjunction(_))
-        );
+    while r != 0 {
+        let quotient = old_r / r;
+        (old_r, r) = (r, old_r - quotient * r);
+        (old_s, s) = (s, old_s - quotient * s);
+    }
+
+    old_s.rem_euclid(modulus)
+}
+
+// The conjunction sending pulses to `target` and its inputs.
+fn find_feeder<'a>(module_configuration: &ModuleConfiguration<'a>, target: &str) -> Result<(&'a str, Vec<&'a str>), StructureError<'a>> {
+    let modules = &module_configuration.modules;
+    let feeders: Vec<usize> = (0..modules.len())
+        .filter(|&id| modules[id].connected_to.iter().any(|&to| modules[to].name == target))
+        .collect();
+
+    let feeder = match feeders[..] {
+        [] => return Err(StructureError::NoFeeder),
+        [feeder] => &modules[feeder],
+        _ => return Err(StructureError::SeveralFeeders(feeders.iter().map(|&id| modules[id].name).collect())),
+    };
+
+    let ModuleType::Conjunction(inputs) = &feeder.module_type else {
+        return Err(StructureError::NotAConjunction(feeder.name));
+    };
+
+    let mut inputs: Vec<&'a str> = inputs.keys().map(|&id| modules[id].name).collect();
+    inputs.sort();
+    Ok((feeder.name, inputs))
+}
+
+// The conjunction before `rx` sends it a low pulse when all its inputs sent it a high pulse during the same press.
+// Each input is expected to send a single high pulse, followed by a low one, every time a cycle of presses ends.
+// The first press where those cycles line up is found with the Chinese remainder theorem, which is the lowest
+// common multiple of the cycles when they all start at the first press.
+fn part2(mut module_configuration: ModuleConfiguration) -> Result<u64, StructureError> {
+    let (feeder, inputs) = find_feeder(&module_configuration, "rx")?;
+    let mut high_presses: Vec<Vec<u64>> = vec![vec![]; inputs.len()];
+
+    while high_presses.iter().any(|presses| presses.len() < CYCLE_SAMPLES) && module_configuration.presses < MAX_PRESSES {
+        let mut pulses: Vec<Vec<Pulse>> = vec![vec![]; inputs.len()];
 
-        if is_connected_to_conjunction {
-            number |= mask;
+        for event in module_configuration.press_button() {
+            if event.is_received_by("rx", Pulse::Low) {
+                return Ok(event.press);
+            }
+            if event.to == feeder {
+                let input = inputs.iter().position(|&input| input == event.from).expect("only inputs send pulses to the conjunction");
+                pulses[input].push(event.pulse);
+            }
         }
 
-        mask <<= 1;
+        for (input, pulses) in pulses.iter().enumerate() {
+            let highs = pulses.iter().filter(|&&pulse| pulse == Pulse::High).count();
+            if highs == 0 {
+                continue;
+            }
+            if highs > 1 || pulses.last() != Some(&Pulse::Low) {
+                return Err(StructureError::NotAPulse { input: inputs[input], press: module_configuration.presses });
+            }
+            high_presses[input].push(module_configuration.presses);
+        }
     }
-    number
-}
 
-fn get_numbers_from_binary_counter_group(module_configuration: &ModuleConfiguration) -> Vec<u64> {
-    let module = &module_configuration.modules[module_configuration.broadcaster_id];
-    module.connected_to
-        .iter()
-        .map(|connected_to_index| binary_counter_group(*connected_to_index, module_configuration))
-        .map(|group| get_binary_number(&group, module_configuration))
-        .collect()
-}
+    let mut congruences = vec![];
+    let mut latest_start = 0;
+    for (input, presses) in inputs.iter().zip(high_presses) {
+        let Some(&[first, second, third]) = presses.get(..CYCLE_SAMPLES) else {
+            return Err(StructureError::TooFewHighPulses { input, presses: module_configuration.presses });
+        };
+        if third - second != second - first {
+            return Err(StructureError::IrregularCycle { input, presses });
+        }
+
+        congruences.push((first % (second - first), second - first));
+        latest_start = latest_start.max(first);
+    }
+
+    let (remainder, modulus) = crt(&congruences)?;
 
+    // the first matching press once every input started its cycle
+    latest_start.saturating_sub(remainder).div_ceil(modulus)
+        .checked_mul(modulus)
+        .and_then(|skipped| skipped.checked_add(remainder))
+        .ok_or(StructureError::TooLarge)
+}
 
-// Part 2 based on: https://github.com/ash42/adventofcode/blob/main/adventofcode2023/src/nl/michielgraat/adventofcode2023/day20/Day20.java
-fn part2(module_configuration: ModuleConfiguration) -> u64 {
-    lcm_vec(&get_numbers_from_binary_counter_group(&module_configuration))
+fn print_trace(module_configuration: &mut ModuleConfiguration, presses: u64) {
+    for _ in 0..presses {
+        for event in module_configuration.press_button() {
+            println!("{}.{} {event}", event.press, event.tick);
+        }
+    }
 }
 
 fn main() {
     let input = include_str!("input.txt");
-    let module_configuration = parse(input);
-
-    let instant = Instant::now();
-    let part1 = part1(module_configuration.clone());
-    println!("Part 1: {} in {:?}", part1, instant.elapsed());
-
-    let instant = Instant::now();
-    let part2 = part2(module_configuration);
-    println!("Part 2: {} in {:?}", part2, instant.elapsed());
+    let mut module_configuration = parse(input);
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["trace"] => print_trace(&mut module_configuration, 1),
+        ["trace", presses] => print_trace(&mut module_configuration, presses.parse().expect("presses has to be a number")),
+        ["until", module, direction @ ("sends" | "receives"), pulse] => {
+            let pulse: Pulse = pulse.parse().expect("pulse has to be low or high");
+            let event = module_configuration.press_until(MAX_PRESSES, |event| match *direction {
+                "sends" => event.is_sent_by(module, pulse),
+                _ => event.is_received_by(module, pulse),
+            });
+
+            match event {
+                Some(event) => println!("{}.{} {event}", event.press, event.tick),
+                None => println!("Not found in {MAX_PRESSES} presses"),
+            }
+        }
+        [] => {
+            let instant = Instant::now();
+            let part1 = part1(module_configuration.clone());
+            println!("Part 1: {} in {:?}", part1, instant.elapsed());
+
+            let instant = Instant::now();
+            match part2(module_configuration) {
+                Ok(part2) => println!("Part 2: {} in {:?}", part2, instant.elapsed()),
+                Err(error) => println!("Part 2: {error}"),
+            }
+        }
+        _ => eprintln!("Usage: day-20 [trace [<presses>] | until <module> <sends | receives> <low | high>]"),
+    }
 }
 
-fn parse(input: &'static str) -> ModuleConfiguration {
-    let mut modules_from_name = HashMap::new();
-    let mut modules_from_id = HashMap::new();
+fn parse(input: &str) -> ModuleConfiguration<'_> {
+    let mut definitions = vec![];
 
-    let mut current_id = 0;
     for line in input.lines() {
-        if let Some((mut name, connected_to)) = line.split_once(" -> ") {
-            let module_type = match (&name[0..1], &name[1..]) {
-                ("b", "roadcaster") => {
-                    ModuleType::Broadcaster
-                }
-                ("%", rest) => {
-                    name = rest;
-                    ModuleType::FlipFlop(false)
-                }
-                ("&", rest) => {
-                    name = rest;
-                    ModuleType::Conjunction(HashMap::new())
-                }
+        if let Some((name, connected_to)) = line.split_once(" -> ") {
+            let (name, module_type) = match (&name[0..1], &name[1..]) {
+                ("b", "roadcaster") => (name, ModuleType::Broadcaster),
+                ("%", rest) => (rest, ModuleType::FlipFlop(false)),
+                ("&", rest) => (rest, ModuleType::Conjunction(HashMap::new())),
                 _ => panic!("Unknown module type"),
             };
 
             let connected_to: Vec<&str> = connected_to.split(", ").collect();
-            modules_from_name.insert(name, (current_id, connected_to, module_type));
-            modules_from_id.insert(current_id, name);
-            current_id += 1;
+            definitions.push((name, module_type, connected_to));
         }
     }
 
-    // Conversion from string names to ids
-
-    let mut modules = vec![];
-    let mut broadcaster_id = 0;
+    // Conversion from string names to ids, modules that are never defined are outputs and come last
 
+    let mut ids: HashMap<&str, usize> = definitions.iter().enumerate().map(|(id, (name, _, _))| (*name, id)).collect();
     let mut output_modules = vec![];
+    let mut modules = vec![];
 
-    for id in 0..current_id {
-        let module_name = modules_from_id[&id];
-        let (id, connected_to_str, module_type) = modules_from_name[module_name].clone();
-
+    for (name, module_type, connected_to_str) in definitions {
         let connected_to: Vec<usize> = connected_to_str.into_iter().map(|name| {
-            if let Some(id) = modules_from_name.get(name).map(|(id, _, _)| *id) {
-                id
-            } else {
-                let id = current_id;
-                output_modules.push((id, name));
-                modules_from_name.insert(name, (id, vec![], ModuleType::Output));
-                modules_from_id.insert(current_id, name);
-                current_id += 1;
-                id
-            }
+            let next_id = ids.len();
+            *ids.entry(name).or_insert_with(|| {
+                output_modules.push(name);
+                next_id
+            })
         }).collect();
 
-        if let ModuleType::Broadcaster = module_type {
-            broadcaster_id = id;
-        }
-
         modules.push(Module {
+            name,
             connected_to,
             module_type,
         });
     }
 
-    for _ in output_modules {
+    for name in output_modules {
         modules.push(Module {
+            name,
             connected_to: vec![],
             module_type: ModuleType::Output,
         });
@@ -272,7 +460,8 @@ fn parse(input: &'static str) -> ModuleConfiguration {
         }
     }
 
-    ModuleConfiguration { broadcaster_id, modules }
+    let broadcaster_id = ids.get("broadcaster").copied().unwrap_or(0);
+    ModuleConfiguration { broadcaster_id, modules, presses: 0 }
 }
 
 #[cfg(test)]
@@ -300,4 +489,107 @@ mod tests {
         let module_configuration = parse(input);
         assert_eq!(part1(module_configuration), 11687500);
     }
+
+    #[test]
+    fn test_pulses_of_a_press() {
+        let mut module_configuration = parse("broadcaster -> a, b, c
+%a -> b
+%b -> c
+%c -> inv
+&inv -> a");
+        let events: Vec<PulseEvent> = module_configuration.press_button().collect();
+
+        assert_eq!(events.iter().map(PulseEvent::to_string).collect::<Vec<_>>(), vec![
+            "button -low-> broadcaster",
+            "broadcaster -low-> a",
+            "broadcaster -low-> b",
+            "broadcaster -low-> c",
+            "a -high-> b",
+            "b -high-> c",
+            "c -high-> inv",
+            "inv -low-> a",
+            "a -low-> b",
+            "b -low-> c",
+            "c -low-> inv",
+            "inv -high-> a",
+        ]);
+        assert_eq!(events.iter().map(|event| event.tick).collect::<Vec<_>>(), vec![0, 1, 1, 1, 2, 2, 2, 3, 4, 5, 6, 7]);
+        assert!(events.iter().all(|event| event.press == 1));
+    }
+
+    #[test]
+    fn test_press_until() {
+        let input = r"broadcaster -> a
+%a -> inv, con
+&inv -> b
+%b -> con
+&con -> output";
+
+        let mut module_configuration = parse(input);
+        assert_eq!(module_configuration.modules.iter().map(|module| module.name).collect::<Vec<_>>(), vec!["broadcaster", "a", "inv", "b", "con", "output"]);
+
+        let event = module_configuration.press_until(10, |event| event.is_received_by("output", Pulse::Low));
+        assert_eq!(event, Some(PulseEvent { from: "con", to: "output", pulse: Pulse::Low, press: 1, tick: 5 }));
+
+        let mut module_configuration = parse(input);
+        let event = module_configuration.press_until(10, |event| event.is_sent_by("b", Pulse::Low));
+        assert_eq!(event.map(|event| event.press), Some(3));
+        assert_eq!(module_configuration.presses, 3);
+
+        assert_eq!(parse(input).press_until(10, |event| event.is_sent_by("output", Pulse::High)), None);
+    }
+
+    // two binary counters, resetting after 5 and 7 presses, whose conjunctions feed rx through inverters
+    const COUNTERS: &str = r"broadcaster -> a0, b0
+%a0 -> a1, ca
+%a1 -> a2
+%a2 -> ca
+&ca -> a0, a1, ia
+&ia -> fd
+%b0 -> b1, cb
+%b1 -> b2, cb
+%b2 -> cb
+&cb -> b0, ib
+&ib -> fd
+&fd -> rx";
+
+    #[test]
+    fn test_part2_from_cycles() {
+        let brute_force = parse(COUNTERS).press_until(MAX_PRESSES, |event| event.is_received_by("rx", Pulse::Low));
+
+        assert_eq!(part2(parse(COUNTERS)), Ok(35));
+        assert_eq!(brute_force.map(|event| event.press), Some(35));
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt(&[(0, 5), (0, 7)]), Ok((0, 35)));
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Ok((23, 105)));
+        assert_eq!(crt(&[(1, 4), (3, 6)]), Ok((9, 12)));
+        assert_eq!(crt(&[(1, 4), (2, 6)]), Err(StructureError::CyclesNeverMatch));
+    }
+
+    #[test]
+    fn test_crt_overflow() {
+        let large = 1 << 40;
+
+        assert_eq!(crt(&[(0, large), (0, large)]), Ok((0, large)));
+        assert_eq!(crt(&[(0, large), (0, large + 1)]), Err(StructureError::TooLarge));
+        assert_eq!(crt(&[(0, u64::MAX), (0, u64::MAX - 1)]), Err(StructureError::TooLarge));
+    }
+
+    #[test]
+    fn test_structure_errors() {
+        assert_eq!(part2(parse("broadcaster -> a\n%a -> output")), Err(StructureError::NoFeeder));
+        assert_eq!(part2(parse("broadcaster -> a\n%a -> rx")), Err(StructureError::NotAConjunction("a")));
+        assert_eq!(part2(parse("broadcaster -> a, b\n%a -> rx\n%b -> rx")), Err(StructureError::SeveralFeeders(vec!["a", "b"])));
+
+        // z stays high after its high pulse
+        let error = part2(parse("broadcaster -> z\n%z -> fd\n&never -> fd\n&fd -> rx")).unwrap_err();
+        assert_eq!(error, StructureError::NotAPulse { input: "z", press: 1 });
+        assert_eq!(error.to_string(), "z doesn't send a single high pulse followed by a low one at press 1");
+
+        let error = part2(parse("broadcaster -> fd\n&never -> fd\n&fd -> rx")).unwrap_err();
+        assert_eq!(error, StructureError::TooFewHighPulses { input: "broadcaster", presses: MAX_PRESSES });
+    }
 }
@@ -1,4 +1,6 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
+
+use grid_pathfinding::Pathfinder;
 
 #[derive(Eq, PartialEq)]
 enum Tile {
@@ -6,34 +8,6 @@ enum Tile {
     Rock,
 }
 
-enum Direction {
-    North,
-    South,
-    East,
-    West,
-}
-
-impl Direction {
-    fn apply_to_position(&self, (x, y): (usize, usize)) -> Option<(usize, usize)> {
-        Some(match self {
-            Direction::North => (x, y.checked_sub(1)?),
-            Direction::South => (x, y + 1),
-            Direction::East => (x + 1, y),
-            Direction::West => (x.checked_sub(1)?, y),
-        })
-    }
-
-    fn apply_to_position_with_margins(&self, (x, y): (usize, usize), (width, height): (usize, usize)) -> Option<(usize, usize)> {
-        let (x, y) = self.apply_to_position((x, y))?;
-
-        if x >= width || y >= height {
-            None
-        } else {
-            Some((x, y))
-        }
-    }
-}
-
 struct Map {
     starting_position: (usize, usize),
     grid: Vec<Vec<Tile>>,
@@ -41,40 +15,20 @@ struct Map {
 
 impl Map {
     fn width(&self) -> usize {
-        self.grid.get(0).map(Vec::len).unwrap_or(0)
+        self.grid.first().map(Vec::len).unwrap_or(0)
     }
 
     fn height(&self) -> usize {
         self.grid.len()
     }
 
+    // Fewest steps to reach every garden plot from the starting position.
     fn get_all_visited(&self) -> HashMap<(usize, usize), usize> {
-        let current = self.starting_position.clone();
-
-        let mut visited = HashMap::new();
-
-        let mut stack = VecDeque::new();
-        stack.push_back((current, 0));
-
-        let map_size = (self.width(), self.height());
-
-        while let Some((position, steps)) = stack.pop_front() {
-            if visited.contains_key(&position) {
-                continue;
-            }
-
-            visited.insert(position, steps);
-
-            for direction in [Direction::North, Direction::South, Direction::West, Direction::East] {
-                if let Some((new_x, new_y)) = direction.apply_to_position_with_margins(position, map_size) {
-                    if self.grid[new_y][new_x] == Tile::GardenPlot {
-                        stack.push_back(((new_x, new_y), steps + 1))
-                    }
-                }
-            }
-        }
-
-        visited
+        Pathfinder::new(self.width(), self.height(), |(x, y), _| (self.grid[y][x] == Tile::GardenPlot).then_some(1))
+            .costs_from(self.starting_position)
+            .into_iter()
+            .map(|(position, steps)| (position, steps as usize))
+            .collect()
     }
 }
 
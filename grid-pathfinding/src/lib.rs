@@ -0,0 +1,376 @@
+// Shortest paths on a grid where moves are constrained by the direction the walker is facing: how many times
+// it has to (or may) keep going straight, which turns it is allowed to make and how much each move costs.
+// The search runs over (position, direction, run length) states, using a heuristic to turn into A* if given one.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+pub type Position = (usize, usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+pub const DIRECTIONS: [Direction; 4] = [Direction::North, Direction::East, Direction::South, Direction::West];
+
+impl Direction {
+    pub fn apply_to_position(&self, (x, y): Position, (width, height): (usize, usize)) -> Option<Position> {
+        let (x, y) = match self {
+            Direction::North => (x, y.checked_sub(1)?),
+            Direction::East => (x + 1, y),
+            Direction::South => (x, y + 1),
+            Direction::West => (x.checked_sub(1)?, y),
+        };
+
+        if x >= width || y >= height {
+            None
+        } else {
+            Some((x, y))
+        }
+    }
+
+    pub fn opposite(&self) -> Self {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
+
+    pub fn turn_left(&self) -> Self {
+        match self {
+            Direction::North => Direction::West,
+            Direction::East => Direction::North,
+            Direction::South => Direction::East,
+            Direction::West => Direction::South,
+        }
+    }
+
+    pub fn turn_right(&self) -> Self {
+        self.turn_left().opposite()
+    }
+}
+
+// Which changes of direction are allowed. Going straight is always allowed while the run is not too long,
+// and the first move from the start can go in any direction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Turns {
+    pub left: bool,
+    pub right: bool,
+    pub reverse: bool,
+}
+
+impl Turns {
+    pub const ANY: Turns = Turns { left: true, right: true, reverse: true };
+    pub const NO_REVERSING: Turns = Turns { left: true, right: true, reverse: false };
+    pub const LEFT_ONLY: Turns = Turns { left: true, right: false, reverse: false };
+
+    fn allows(&self, from: Direction, to: Direction) -> bool {
+        if to == from {
+            true
+        } else if to == from.opposite() {
+            self.reverse
+        } else if to == from.turn_left() {
+            self.left
+        } else {
+            self.right
+        }
+    }
+}
+
+// A move into `position`, with the cost accumulated since leaving the start.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub position: Position,
+    pub direction: Direction,
+    pub cost: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Path {
+    pub start: Position,
+    pub steps: Vec<Step>,
+}
+
+impl Path {
+    pub fn cost(&self) -> u64 {
+        self.steps.last().map(|step| step.cost).unwrap_or(0)
+    }
+}
+
+// (position, direction of the last move, consecutive moves in that direction), with no direction at the start
+type State = (Position, Option<Direction>, usize);
+
+type CostFn<'a> = Box<dyn Fn(Position, Direction) -> Option<u64> + 'a>;
+type HeuristicFn<'a> = Box<dyn Fn(Position, Position) -> u64 + 'a>;
+
+pub struct Pathfinder<'a> {
+    width: usize,
+    height: usize,
+    // cost of moving into a position in a direction, None when the move is not possible
+    cost: CostFn<'a>,
+    turns: Turns,
+    min_run: usize,
+    max_run: Option<usize>,
+    // lower bound of the cost from a position to the goal, which must never decrease by more than a move costs
+    heuristic: Option<HeuristicFn<'a>>,
+}
+
+impl<'a> Pathfinder<'a> {
+    // Any turn is allowed and there is no limit on how long the walker goes straight.
+    pub fn new(width: usize, height: usize, cost: impl Fn(Position, Direction) -> Option<u64> + 'a) -> Self {
+        Pathfinder { width, height, cost: Box::new(cost), turns: Turns::ANY, min_run: 1, max_run: None, heuristic: None }
+    }
+
+    pub fn turns(mut self, turns: Turns) -> Self {
+        self.turns = turns;
+        self
+    }
+
+    // The walker has to move at least `min_run` times in the same direction before it can turn or stop,
+    // and at most `max_run` times if there is a maximum.
+    pub fn runs(mut self, min_run: usize, max_run: Option<usize>) -> Self {
+        assert!(min_run >= 1, "a run is at least one move long");
+        assert!(max_run.is_none_or(|max_run| max_run >= min_run), "the maximum run can't be below the minimum");
+
+        self.min_run = min_run;
+        self.max_run = max_run;
+        self
+    }
+
+    pub fn heuristic(mut self, heuristic: impl Fn(Position, Position) -> u64 + 'a) -> Self {
+        self.heuristic = Some(Box::new(heuristic));
+        self
+    }
+
+    fn can_stop(&self, (_, direction, run): State) -> bool {
+        direction.is_none() || run >= self.min_run
+    }
+
+    fn moves(&self, (position, direction, run): State) -> impl Iterator<Item=(State, u64)> + '_ {
+        DIRECTIONS.into_iter().filter_map(move |next_direction| {
+            let next_run = match direction {
+                None => 1,
+                Some(direction) if direction == next_direction => run + 1,
+                Some(direction) if run >= self.min_run && self.turns.allows(direction, next_direction) => 1,
+                Some(_) => return None,
+            };
+            if self.max_run.is_some_and(|max_run| next_run > max_run) {
+                return None;
+            }
+
+            // without a maximum, runs longer than the minimum all behave the same
+            let next_run = if self.max_run.is_none() { next_run.min(self.min_run) } else { next_run };
+
+            let next_position = next_direction.apply_to_position(position, (self.width, self.height))?;
+            let cost = (self.cost)(next_position, next_direction)?;
+            Some(((next_position, Some(next_direction), next_run), cost))
+        })
+    }
+
+    // States are numbered so the search can keep its costs in flat vectors instead of hash maps.
+    fn run_count(&self) -> usize {
+        self.max_run.unwrap_or(self.min_run) + 1
+    }
+
+    fn state_index(&self, ((x, y), direction, run): State) -> usize {
+        let direction = direction.map(|direction| direction as usize).unwrap_or(DIRECTIONS.len());
+        ((y * self.width + x) * (DIRECTIONS.len() + 1) + direction) * self.run_count() + run
+    }
+
+    fn state_at(&self, index: usize) -> State {
+        let (index, run) = (index / self.run_count(), index % self.run_count());
+        let (index, direction) = (index / (DIRECTIONS.len() + 1), index % (DIRECTIONS.len() + 1));
+        ((index % self.width, index / self.width), DIRECTIONS.get(direction).copied(), run)
+    }
+
+    // Dijkstra (or A*) from `start`, stopping as soon as it can stop at `goal`, or reaching everything without one.
+    fn search(&self, start: Position, goal: Option<Position>) -> Search {
+        let estimate = |position| match (&self.heuristic, goal) {
+            (Some(heuristic), Some(goal)) => heuristic(position, goal),
+            _ => 0,
+        };
+
+        let states = self.width * self.height * (DIRECTIONS.len() + 1) * self.run_count();
+        let mut search = Search { costs: vec![None; states], previous: vec![None; states], end: None };
+
+        let start_index = self.state_index((start, None, 0));
+        search.costs[start_index] = Some(0);
+        let mut heap = BinaryHeap::from([Reverse((estimate(start), 0, start_index))]);
+
+        while let Some(Reverse((_, cost, index))) = heap.pop() {
+            if search.costs[index].is_some_and(|known| known < cost) {
+                continue;
+            }
+
+            let state = self.state_at(index);
+            if Some(state.0) == goal && self.can_stop(state) {
+                search.end = Some(index);
+                break;
+            }
+
+            for (next, move_cost) in self.moves(state) {
+                let next_cost = cost + move_cost;
+                let next_index = self.state_index(next);
+                if search.costs[next_index].is_none_or(|known| next_cost < known) {
+                    search.costs[next_index] = Some(next_cost);
+                    search.previous[next_index] = Some(index);
+                    heap.push(Reverse((next_cost + estimate(next.0), next_cost, next_index)));
+                }
+            }
+        }
+
+        search
+    }
+
+    pub fn find_path(&self, start: Position, goal: Position) -> Option<Path> {
+        let search = self.search(start, Some(goal));
+
+        let mut indices = vec![search.end?];
+        while let Some(index) = search.previous[*indices.last().expect("there is always a last state")] {
+            indices.push(index);
+        }
+
+        let steps = indices.into_iter().rev()
+            .filter_map(|index| {
+                let (position, direction, _) = self.state_at(index);
+                Some(Step { position, direction: direction?, cost: search.costs[index]? })
+            })
+            .collect();
+
+        Some(Path { start, steps })
+    }
+
+    // The lowest cost to stop at every reachable position.
+    pub fn costs_from(&self, start: Position) -> HashMap<Position, u64> {
+        let mut result = HashMap::new();
+
+        for (index, cost) in self.search(start, None).costs.into_iter().enumerate() {
+            let state = self.state_at(index);
+            if let Some(cost) = cost.filter(|_| self.can_stop(state)) {
+                result.entry(state.0).and_modify(|known: &mut u64| *known = (*known).min(cost)).or_insert(cost);
+            }
+        }
+
+        result
+    }
+}
+
+struct Search {
+    costs: Vec<Option<u64>>,
+    // the state every state was reached from with its lowest cost
+    previous: Vec<Option<usize>>,
+    end: Option<usize>,
+}
+
+// Admissible heuristic for grids where every move costs at least `min_cost`.
+pub fn manhattan_distance(min_cost: u64) -> impl Fn(Position, Position) -> u64 {
+    move |(x, y), (goal_x, goal_y)| (x.abs_diff(goal_x) + y.abs_diff(goal_y)) as u64 * min_cost
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_digits(input: &str) -> Vec<Vec<u64>> {
+        input.lines().map(|line| line.chars().map(|c| c.to_digit(10).unwrap() as u64).collect()).collect()
+    }
+
+    fn digit_cost(grid: &[Vec<u64>]) -> impl Fn(Position, Direction) -> Option<u64> + '_ {
+        |(x, y), _| Some(grid[y][x])
+    }
+
+    // every step is one move away from the previous one and accumulates the cost of entering it
+    fn assert_connected(path: &Path, grid: &[Vec<u64>]) {
+        let size = (grid[0].len(), grid.len());
+        let mut position = path.start;
+        let mut cost = 0;
+
+        for step in &path.steps {
+            position = step.direction.apply_to_position(position, size).unwrap();
+            cost += grid[position.1][position.0];
+            assert_eq!((step.position, step.cost), (position, cost));
+        }
+    }
+
+    #[test]
+    fn test_unconstrained() {
+        let grid = parse_digits("1199\n9199\n1111");
+        let pathfinder = Pathfinder::new(4, 3, digit_cost(&grid));
+
+        let path = pathfinder.find_path((0, 0), (3, 2)).unwrap();
+        assert_eq!(path.cost(), 5);
+        assert_connected(&path, &grid);
+
+        assert_eq!(pathfinder.find_path((2, 1), (2, 1)).unwrap().steps, vec![]);
+        assert_eq!(pathfinder.costs_from((0, 0))[&(0, 2)], 4);
+    }
+
+    #[test]
+    fn test_blocked_cells() {
+        let grid = ["..#", "#.#", "..#"].map(|row| row.chars().map(|c| c == '.').collect::<Vec<_>>());
+        let pathfinder = Pathfinder::new(3, 3, |(x, y): Position, _| grid[y][x].then_some(1));
+
+        let costs = pathfinder.costs_from((0, 0));
+        assert_eq!(costs.len(), 5);
+        assert_eq!(costs[&(0, 2)], 4);
+        assert_eq!(pathfinder.find_path((0, 0), (2, 2)), None);
+    }
+
+    #[test]
+    fn test_runs() {
+        let grid = parse_digits("111111111111\n999999999991\n999999999991\n999999999991\n999999999991");
+        let pathfinder = Pathfinder::new(12, 5, digit_cost(&grid)).turns(Turns::NO_REVERSING);
+
+        let path = pathfinder.find_path((0, 0), (11, 4)).unwrap();
+        assert_eq!(path.cost(), 15);
+
+        let path = pathfinder.runs(4, Some(10)).find_path((0, 0), (11, 4)).unwrap();
+        assert_eq!(path.cost(), 71);
+        assert_connected(&path, &grid);
+    }
+
+    #[test]
+    fn test_turn_rules() {
+        // going right from the start and then down needs a right turn, or three left turns around a loop
+        let grid = parse_digits("111\n111\n111");
+        let left_only = Pathfinder::new(3, 3, digit_cost(&grid)).turns(Turns::LEFT_ONLY);
+        let path = left_only.find_path((0, 0), (1, 1)).unwrap();
+        assert_connected(&path, &grid);
+        assert!(path.steps.windows(2).all(|pair| {
+            pair[0].direction == pair[1].direction || pair[1].direction == pair[0].direction.turn_left()
+        }));
+
+        // stopping next to the start after a run of two means going past it and coming back
+        let corridor = Pathfinder::new(5, 1, |(x, _): Position, _| (x != 4).then_some(1)).runs(2, None);
+        assert_eq!(corridor.find_path((0, 0), (1, 0)).map(|path| path.cost()), Some(5));
+        assert_eq!(corridor.turns(Turns::NO_REVERSING).find_path((0, 0), (1, 0)), None);
+    }
+
+    #[test]
+    fn test_direction_costs() {
+        // moving north is free, every other move costs 5
+        let pathfinder = Pathfinder::new(3, 3, |_, direction| Some(if direction == Direction::North { 0 } else { 5 }));
+
+        assert_eq!(pathfinder.find_path((1, 2), (1, 0)).map(|path| path.cost()), Some(0));
+        assert_eq!(pathfinder.find_path((1, 0), (1, 2)).map(|path| path.cost()), Some(10));
+    }
+
+    #[test]
+    fn test_heuristic_gives_same_cost() {
+        let grid = parse_digits("2413432311323\n3215453535623\n3255245654254\n3446585845452\n4546657867536");
+        let dijkstra = Pathfinder::new(13, 5, digit_cost(&grid)).turns(Turns::NO_REVERSING).runs(1, Some(3));
+        let a_star = Pathfinder::new(13, 5, digit_cost(&grid)).turns(Turns::NO_REVERSING).runs(1, Some(3))
+            .heuristic(manhattan_distance(1));
+
+        let expected = dijkstra.find_path((0, 0), (12, 4)).unwrap();
+        let path = a_star.find_path((0, 0), (12, 4)).unwrap();
+        assert_eq!(path.cost(), expected.cost());
+        assert_connected(&path, &grid);
+    }
+}
@@ -0,0 +1,229 @@
+// Polygons with vertices on integer coordinates: area with the shoelace formula, and how many lattice points
+// are inside and on the boundary with Pick's theorem. Useful for anything that walks a closed loop on a grid.
+
+pub type Point = (i64, i64);
+
+// In the usual maths axes, where y grows upwards. On grids where y grows downwards it looks the other way around.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    Clockwise,
+    CounterClockwise,
+    // no area at all, like a polygon that goes back over itself
+    Degenerate,
+}
+
+// Vertices in order, with an edge from the last one back to the first one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Polygon {
+    vertices: Vec<Point>,
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
+fn cross(origin: Point, a: Point, b: Point) -> i128 {
+    let (ax, ay) = ((a.0 - origin.0) as i128, (a.1 - origin.1) as i128);
+    let (bx, by) = ((b.0 - origin.0) as i128, (b.1 - origin.1) as i128);
+    ax * by - ay * bx
+}
+
+fn dot(origin: Point, a: Point, b: Point) -> i128 {
+    let (ax, ay) = ((a.0 - origin.0) as i128, (a.1 - origin.1) as i128);
+    let (bx, by) = ((b.0 - origin.0) as i128, (b.1 - origin.1) as i128);
+    ax * bx + ay * by
+}
+
+// Whether `point`, known to be on the line through the segment, is between its ends.
+fn within(point: Point, (start, end): (Point, Point)) -> bool {
+    (start.0.min(end.0)..=start.0.max(end.0)).contains(&point.0)
+        && (start.1.min(end.1)..=start.1.max(end.1)).contains(&point.1)
+}
+
+fn segments_touch(a: (Point, Point), b: (Point, Point)) -> bool {
+    let (d1, d2) = (cross(a.0, a.1, b.0).signum(), cross(a.0, a.1, b.1).signum());
+    let (d3, d4) = (cross(b.0, b.1, a.0).signum(), cross(b.0, b.1, a.1).signum());
+
+    (d1 * d2 < 0 && d3 * d4 < 0)
+        || (d1 == 0 && within(b.0, a))
+        || (d2 == 0 && within(b.1, a))
+        || (d3 == 0 && within(a.0, b))
+        || (d4 == 0 && within(a.1, b))
+}
+
+impl Polygon {
+    pub fn new(vertices: Vec<Point>) -> Polygon {
+        Polygon { vertices }
+    }
+
+    // Walks `length` units in each direction, given as unit vectors like (1, 0), starting at `start`.
+    pub fn from_steps(start: Point, steps: impl IntoIterator<Item=((i64, i64), i64)>) -> Polygon {
+        let mut position = start;
+        let mut vertices = vec![start];
+
+        for ((dx, dy), length) in steps {
+            position = (position.0 + dx * length, position.1 + dy * length);
+            vertices.push(position);
+        }
+
+        // the walk ends where it started, which is already the first vertex
+        if vertices.len() > 1 && vertices.last() == vertices.first() {
+            vertices.pop();
+        }
+
+        Polygon { vertices }
+    }
+
+    pub fn vertices(&self) -> &[Point] {
+        &self.vertices
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item=(Point, Point)> + '_ {
+        self.vertices.iter().zip(self.vertices.iter().cycle().skip(1)).map(|(&start, &end)| (start, end))
+    }
+
+    // Shoelace formula. Twice the area is always an integer, positive when counter-clockwise.
+    pub fn signed_double_area(&self) -> i128 {
+        self.edges().map(|((x1, y1), (x2, y2))| x1 as i128 * y2 as i128 - x2 as i128 * y1 as i128).sum()
+    }
+
+    pub fn area(&self) -> f64 {
+        self.signed_double_area().abs() as f64 / 2.0
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        match self.signed_double_area().signum() {
+            1 => Orientation::CounterClockwise,
+            -1 => Orientation::Clockwise,
+            _ => Orientation::Degenerate,
+        }
+    }
+
+    pub fn perimeter(&self) -> f64 {
+        self.edges()
+            .map(|((x1, y1), (x2, y2))| ((x2 - x1) as f64).hypot((y2 - y1) as f64))
+            .sum()
+    }
+
+    // Lattice points on the edges, vertices included.
+    pub fn boundary_points(&self) -> i64 {
+        self.edges().map(|((x1, y1), (x2, y2))| gcd(x2 - x1, y2 - y1)).sum()
+    }
+
+    // Lattice points strictly inside, from Pick's theorem: A = I + B / 2 - 1.
+    // Only meaningful for simple polygons.
+    pub fn interior_points(&self) -> i64 {
+        ((self.signed_double_area().abs() - self.boundary_points() as i128 + 2) / 2) as i64
+    }
+
+    // Lattice points inside or on the boundary, e.g. the tiles dug by a trench loop through tile centres.
+    pub fn enclosed_points(&self) -> i64 {
+        self.interior_points() + self.boundary_points()
+    }
+
+    // Indices of the first two edges that cross or touch, other than consecutive edges meeting at their shared vertex.
+    // Edges are compared pairwise, so this takes quadratic time in the number of edges.
+    pub fn self_intersection(&self) -> Option<(usize, usize)> {
+        let edges: Vec<_> = self.edges().collect();
+        let count = edges.len();
+
+        for (i, &first) in edges.iter().enumerate() {
+            for (j, &second) in edges.iter().enumerate().skip(i + 1) {
+                let adjacent = j == i + 1 || (i == 0 && j == count - 1);
+
+                let intersects = if first.0 == first.1 || second.0 == second.1 {
+                    // a repeated vertex is only a problem away from the edges next to it
+                    !adjacent && segments_touch(first, second)
+                } else if adjacent {
+                    // consecutive edges meet at a vertex, they only overlap if one goes back along the other
+                    let (shared, first_end, second_end) = if j == i + 1 {
+                        (first.1, first.0, second.1)
+                    } else {
+                        (first.0, first.1, second.0)
+                    };
+                    cross(shared, first_end, second_end) == 0 && dot(shared, first_end, second_end) > 0
+                } else {
+                    segments_touch(first, second)
+                };
+
+                if intersects {
+                    return Some((i, j));
+                }
+            }
+        }
+
+        None
+    }
+
+    pub fn is_simple(&self) -> bool {
+        self.self_intersection().is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_square() {
+        let square = Polygon::new(vec![(0, 0), (3, 0), (3, 3), (0, 3)]);
+
+        assert_eq!(square.signed_double_area(), 18);
+        assert_eq!(square.area(), 9.0);
+        assert_eq!(square.perimeter(), 12.0);
+        assert_eq!(square.boundary_points(), 12);
+        assert_eq!(square.interior_points(), 4);
+        assert_eq!(square.enclosed_points(), 16);
+        assert_eq!(square.orientation(), Orientation::CounterClockwise);
+        assert!(square.is_simple());
+    }
+
+    #[test]
+    fn test_pick_on_triangle() {
+        let triangle = Polygon::new(vec![(0, 0), (0, 4), (4, 0)]);
+
+        assert_eq!(triangle.area(), 8.0);
+        assert_eq!(triangle.boundary_points(), 12);
+        assert_eq!(triangle.interior_points(), 3);
+        assert_eq!(triangle.orientation(), Orientation::Clockwise);
+        assert!((triangle.perimeter() - (8.0 + 32f64.sqrt())).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_from_steps() {
+        let steps = [((1, 0), 6), ((0, 1), 5), ((-1, 0), 2), ((0, 1), 2), ((-1, 0), 4), ((0, -1), 7)];
+        let polygon = Polygon::from_steps((0, 0), steps);
+
+        assert_eq!(polygon.vertices(), &[(0, 0), (6, 0), (6, 5), (4, 5), (4, 7), (0, 7)]);
+        assert_eq!(polygon.boundary_points(), 26);
+        assert_eq!(polygon.enclosed_points(), 7 * 8 - 2 * 2);
+    }
+
+    #[test]
+    fn test_self_intersection() {
+        let bow_tie = Polygon::new(vec![(0, 0), (2, 2), (2, 0), (0, 2)]);
+        assert_eq!(bow_tie.self_intersection(), Some((0, 2)));
+        assert_eq!(bow_tie.orientation(), Orientation::Degenerate);
+
+        // touching itself at a vertex counts, even though no edges cross
+        let pinched = Polygon::new(vec![(0, 0), (2, 0), (1, 1), (2, 2), (0, 2), (1, 1)]);
+        assert!(!pinched.is_simple());
+
+        // going back over the previous edge
+        let spike = Polygon::new(vec![(0, 0), (4, 0), (2, 0), (2, 2)]);
+        assert_eq!(spike.self_intersection(), Some((0, 1)));
+
+        // straight edges split in several collinear ones are fine
+        let collinear = Polygon::new(vec![(0, 0), (1, 0), (2, 0), (2, 1), (2, 2), (0, 2)]);
+        assert!(collinear.is_simple());
+    }
+
+    #[test]
+    fn test_huge_coordinates() {
+        let side = 2_000_000_000;
+        let square = Polygon::new(vec![(0, 0), (side, 0), (side, side), (0, side)]);
+
+        assert_eq!(square.signed_double_area(), 2 * side as i128 * side as i128);
+        assert_eq!(square.enclosed_points(), (side + 1) * (side + 1));
+    }
+}